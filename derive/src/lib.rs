@@ -0,0 +1,244 @@
+//! Derive macros for [`moonshine-util`](https://crates.io/crates/moonshine-util).
+//!
+//! These macros are re-exported by `moonshine-util` with the `derive` feature, and should not be used directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Error, Fields, Ident, Path, Token, Type,
+};
+
+/// Derives `MapQuery` for a struct whose fields are each mapped from a component.
+///
+/// See `moonshine_util::query::MapQuery` for more information.
+#[proc_macro_derive(MapQuery, attributes(map_query))]
+pub fn derive_map_query(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_map_query(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Arguments of a `#[map_query(crate = path)]` struct attribute.
+struct CratePath(Path);
+
+impl Parse for CratePath {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token![crate]>()?;
+        input.parse::<Token![=]>()?;
+        let path = input.parse()?;
+        input.parse::<Option<Token![,]>>()?;
+
+        if !input.is_empty() {
+            return Err(input.error("unexpected tokens in `map_query` attribute"));
+        }
+
+        Ok(Self(path))
+    }
+}
+
+/// Arguments of a `#[map_query(Component, map = path)]` field attribute.
+struct FieldSource {
+    component: Type,
+    map: Option<Path>,
+}
+
+impl Parse for FieldSource {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let component = input.parse()?;
+        let mut map = None;
+
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let key: Ident = input.parse()?;
+            if key != "map" {
+                return Err(Error::new(key.span(), "expected `map = <path>`"));
+            }
+            input.parse::<Token![=]>()?;
+            map = Some(input.parse()?);
+            input.parse::<Option<Token![,]>>()?;
+        }
+
+        if !input.is_empty() {
+            return Err(input.error("unexpected tokens in `map_query` attribute"));
+        }
+
+        Ok(Self { component, map })
+    }
+}
+
+fn expand_map_query(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "`MapQuery` can only be derived for structs",
+        ));
+    };
+
+    let mut krate: Path = parse_quote!(::moonshine_util);
+    for attr in &input.attrs {
+        if attr.path().is_ident("map_query") {
+            krate = attr.parse_args::<CratePath>()?.0;
+        }
+    }
+
+    let mut components = Vec::new();
+    let mut bindings = Vec::new();
+    let mut values = Vec::new();
+    for (index, field) in data.fields.iter().enumerate() {
+        let attr = field
+            .attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("map_query"))
+            .ok_or_else(|| {
+                Error::new(
+                    field.span(),
+                    "missing `#[map_query(Component)]` attribute on field",
+                )
+            })?;
+        let FieldSource { component, map } = attr.parse_args()?;
+
+        let binding = format_ident!("data_{}", index);
+        values.push(match map {
+            Some(map) => quote!(#map(#binding)),
+            None => quote!(::core::clone::Clone::clone(#binding)),
+        });
+        components.push(component);
+        bindings.push(binding);
+    }
+
+    let output = match &data.fields {
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|field| &field.ident);
+            quote!(Self { #(#names: #values),* })
+        }
+        Fields::Unnamed(_) => quote!(Self(#(#values),*)),
+        Fields::Unit => quote!(Self),
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #krate::query::MapQuery for #ident #type_generics #where_clause {
+            type Query = (#(&'static #components,)*);
+
+            type Output = Self;
+
+            fn map((#(#bindings,)*): (#(&#components,)*)) -> Self {
+                #output
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_error(input: DeriveInput) -> String {
+        expand_map_query(input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn crate_path() {
+        let output = expand_map_query(parse_quote! {
+            struct View {
+                #[map_query(A)]
+                a: A,
+            }
+        })
+        .unwrap();
+        assert!(output
+            .to_string()
+            .contains(":: moonshine_util :: query :: MapQuery"));
+
+        let output = expand_map_query(parse_quote! {
+            #[map_query(crate = ::util)]
+            struct View {
+                #[map_query(A)]
+                a: A,
+            }
+        })
+        .unwrap();
+        assert!(output.to_string().contains(":: util :: query :: MapQuery"));
+    }
+
+    #[test]
+    fn not_a_struct() {
+        let error = expand_error(parse_quote! {
+            enum View {
+                A,
+            }
+        });
+        assert_eq!(error, "`MapQuery` can only be derived for structs");
+    }
+
+    #[test]
+    fn missing_attribute() {
+        let error = expand_error(parse_quote! {
+            struct View {
+                a: A,
+            }
+        });
+        assert_eq!(
+            error,
+            "missing `#[map_query(Component)]` attribute on field"
+        );
+    }
+
+    #[test]
+    fn unknown_key() {
+        let error = expand_error(parse_quote! {
+            struct View {
+                #[map_query(A, with = f)]
+                a: A,
+            }
+        });
+        assert_eq!(error, "expected `map = <path>`");
+    }
+
+    #[test]
+    fn missing_component() {
+        let error = expand_error(parse_quote! {
+            struct View {
+                #[map_query()]
+                a: A,
+            }
+        });
+        assert!(error.starts_with("unexpected end of input"), "{error}");
+    }
+
+    #[test]
+    fn unexpected_tokens() {
+        let error = expand_error(parse_quote! {
+            struct View {
+                #[map_query(A, map = f, B)]
+                a: A,
+            }
+        });
+        assert_eq!(error, "unexpected tokens in `map_query` attribute");
+
+        let error = expand_error(parse_quote! {
+            #[map_query(crate = util, B)]
+            struct View {
+                #[map_query(A)]
+                a: A,
+            }
+        });
+        assert_eq!(error, "unexpected tokens in `map_query` attribute");
+    }
+
+    #[test]
+    fn invalid_crate_path() {
+        let error = expand_error(parse_quote! {
+            #[map_query(util)]
+            struct View {
+                #[map_query(A)]
+                a: A,
+            }
+        });
+        assert_eq!(error, "expected `crate`");
+    }
+}
@@ -6,6 +6,7 @@ use bevy_ecs::component::Mutable;
 use bevy_ecs::lifecycle::HookContext;
 use bevy_ecs::prelude::*;
 use bevy_ecs::world::DeferredWorld;
+use bevy_log::prelude::*;
 
 use crate::Static;
 
@@ -17,6 +18,51 @@ pub trait MergeComponent: Component<Mutability = Mutable> {
     fn merge(&mut self, other: Self);
 }
 
+/// Any [`MergeComponent`] or a tuple of them which can be merged into an [`Entity`].
+///
+/// Each element of a tuple is merged into the entity if it already exists, or inserted otherwise.
+///
+/// See [`Merge<T>`] for detailed usage and examples.
+pub trait MergeBundle: Static {
+    /// Merges the contents of this bundle into the given `entity`.
+    fn merge_into(self, entity: &mut EntityWorldMut);
+}
+
+impl<T: MergeComponent> MergeBundle for T {
+    fn merge_into(self, entity: &mut EntityWorldMut) {
+        if let Some(mut target) = entity.get_mut::<T>() {
+            target.merge(self);
+        } else {
+            entity.insert(self);
+        }
+    }
+}
+
+macro_rules! impl_merge_bundle {
+    ($($t:ident),*) => {
+        impl<$($t: MergeBundle),*> MergeBundle for ($($t,)*) {
+            #[allow(non_snake_case)]
+            fn merge_into(self, entity: &mut EntityWorldMut) {
+                let ($($t,)*) = self;
+                $($t.merge_into(entity);)*
+            }
+        }
+    };
+}
+
+impl_merge_bundle!(A);
+impl_merge_bundle!(A, B);
+impl_merge_bundle!(A, B, C);
+impl_merge_bundle!(A, B, C, D);
+impl_merge_bundle!(A, B, C, D, E);
+impl_merge_bundle!(A, B, C, D, E, F);
+impl_merge_bundle!(A, B, C, D, E, F, G);
+impl_merge_bundle!(A, B, C, D, E, F, G, H);
+impl_merge_bundle!(A, B, C, D, E, F, G, H, I);
+impl_merge_bundle!(A, B, C, D, E, F, G, H, I, J);
+impl_merge_bundle!(A, B, C, D, E, F, G, H, I, J, K);
+impl_merge_bundle!(A, B, C, D, E, F, G, H, I, J, K, L);
+
 /// An [`EntityCommand`] which is used to add components.
 ///
 /// # Usage
@@ -70,14 +116,46 @@ pub trait MergeComponent: Component<Mutability = Mutable> {
 /// assert_eq!(value, 3);
 /// ```
 ///
+/// Multiple components may also be merged at once using a tuple. See [`MergeBundle`] for details.
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::prelude::*;
+///
+/// #[derive(Component, Default)]
+/// struct N(usize);
+///
+/// impl MergeComponent for N {
+///     fn merge(&mut self, other: Self) {
+///         self.0 += other.0;
+///     }
+/// }
+///
+/// #[derive(Component, Default)]
+/// struct M(usize);
+///
+/// impl MergeComponent for M {
+///     fn merge(&mut self, other: Self) {
+///         self.0 *= other.0;
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// let entity = world.spawn((N(1), Merge((N(2), M(3)))));
+/// let &N(n) = entity.get().unwrap();
+/// let &M(m) = entity.get().unwrap();
+/// assert_eq!(n, 3);
+/// assert_eq!(m, 3);
+/// ```
+///
 /// Because [`Merge<T>`] is a component itself, it can be used as a component requirement.
 /// However, because of the component uniqueness rule, multiple [`Merge<T>`] instances may not exist on the same entity.
 /// To work around this, you can use [`MergeFrom`] and [`MergeWith`].
 #[derive(Component)]
 #[component(on_insert = Self::on_insert)]
-pub struct Merge<T: MergeComponent>(pub T);
+pub struct Merge<T: MergeBundle>(pub T);
 
-impl<T: MergeComponent> Merge<T> {
+impl<T: MergeBundle> Merge<T> {
     /// Ergonomic alias for [`MergeWith::new`].
     pub fn with<F: Static + FnOnce() -> T>(f: F) -> MergeWith<T, impl Static + FnOnce() -> T> {
         MergeWith::new(f)
@@ -93,22 +171,18 @@ impl<T: MergeComponent> Merge<T> {
     }
 }
 
-impl<T: MergeComponent> From<T> for Merge<T> {
+impl<T: MergeBundle> From<T> for Merge<T> {
     fn from(value: T) -> Self {
         Self(value)
     }
 }
 
-impl<T: MergeComponent> EntityCommand for Merge<T> {
+impl<T: MergeBundle> EntityCommand for Merge<T> {
     type Out = ();
 
     fn apply(self, mut entity: EntityWorldMut) {
         let Self(source) = self;
-        if let Some(mut target) = entity.get_mut::<T>() {
-            target.merge(source);
-        } else {
-            entity.insert(source);
-        }
+        source.merge_into(&mut entity);
     }
 }
 
@@ -146,9 +220,9 @@ impl<T: MergeComponent> EntityCommand for Merge<T> {
 /// ```
 #[derive(Component)]
 #[component(on_insert = Self::on_insert)]
-pub struct MergeFrom<M: Static, T: MergeComponent>(Merge<T>, PhantomData<M>);
+pub struct MergeFrom<M: Static, T: MergeBundle>(Merge<T>, PhantomData<M>);
 
-impl<M: Static, T: MergeComponent> MergeFrom<M, T> {
+impl<M: Static, T: MergeBundle> MergeFrom<M, T> {
     fn on_insert(mut world: DeferredWorld, ctx: HookContext) {
         world
             .commands()
@@ -160,7 +234,7 @@ impl<M: Static, T: MergeComponent> MergeFrom<M, T> {
     }
 }
 
-impl<M: Static, T: MergeComponent> From<T> for MergeFrom<M, T> {
+impl<M: Static, T: MergeBundle> From<T> for MergeFrom<M, T> {
     fn from(value: T) -> Self {
         Self(Merge(value), PhantomData)
     }
@@ -191,9 +265,9 @@ impl<M: Static, T: MergeComponent> From<T> for MergeFrom<M, T> {
 /// ```
 #[derive(Component)]
 #[component(on_insert = Self::on_insert)]
-pub struct MergeWith<T: MergeComponent, F: Static + FnOnce() -> T>(F, PhantomData<T>);
+pub struct MergeWith<T: MergeBundle, F: Static + FnOnce() -> T>(F, PhantomData<T>);
 
-impl<F: Static + FnOnce() -> T, T: MergeComponent> MergeWith<T, F> {
+impl<F: Static + FnOnce() -> T, T: MergeBundle> MergeWith<T, F> {
     /// Creates a new [`MergeWith`] [`Component`] for the given [`FnOnce`].
     ///
     /// See [`Merge::with`] for a more ergonomic constructor.
@@ -212,12 +286,304 @@ impl<F: Static + FnOnce() -> T, T: MergeComponent> MergeWith<T, F> {
     }
 }
 
-impl<F: Static + FnOnce() -> T, T: MergeComponent> From<F> for MergeWith<T, F> {
+impl<F: Static + FnOnce() -> T, T: MergeBundle> From<F> for MergeWith<T, F> {
     fn from(f: F) -> Self {
         Self::new(f)
     }
 }
 
+/// A [`Component`] which links an [`Entity`] to many other entities as a many-to-many relationship.
+///
+/// # Usage
+///
+/// Unlike a [`Relationship`](bevy_ecs::relationship::Relationship), which links each source entity to
+/// exactly one target, an entity with [`Links<M>`] may be linked to any number of target entities, and
+/// each target may be linked to by any number of sources. The marker type `M` distinguishes between different
+/// kinds of links.
+///
+/// Each target of a [`Links<M>`] automatically receives a [`LinkedBy<M>`] component which contains
+/// all the entities linked to it. Both sides are kept consistent when links are added or removed,
+/// or when any of the linked entities are despawned.
+///
+/// Links may be added or removed using [`LinkEntities`], or by inserting a new [`Links<M>`] component.
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::prelude::*;
+///
+/// struct Follows;
+///
+/// let mut w = World::new();
+/// let a = w.spawn_empty().id();
+/// let b = w.spawn_empty().id();
+/// let c = w.spawn(Links::<Follows>::new([a, b])).id();
+/// w.entity_mut(b).link::<Follows>(a);
+///
+/// let followers: Vec<Entity> = w.get::<LinkedBy<Follows>>(a).unwrap().iter().collect();
+/// assert_eq!(followers, [c, b]);
+///
+/// w.entity_mut(c).despawn();
+/// let followers: Vec<Entity> = w.get::<LinkedBy<Follows>>(a).unwrap().iter().collect();
+/// assert_eq!(followers, [b]);
+/// assert!(w.get::<LinkedBy<Follows>>(b).is_none());
+/// ```
+///
+/// See [`LinkQuery`](crate::hierarchy::LinkQuery) for traversal of linked entities.
+#[derive(Component)]
+#[component(on_insert = Self::on_insert, on_discard = Self::on_discard)]
+pub struct Links<M: Static>(Vec<Entity>, PhantomData<M>);
+
+impl<M: Static> Links<M> {
+    /// Creates a new [`Links<M>`] component which links to the given entities.
+    ///
+    /// Duplicate entities are ignored.
+    pub fn new(targets: impl IntoIterator<Item = Entity>) -> Self {
+        let mut links = Vec::new();
+        for target in targets {
+            if !links.contains(&target) {
+                links.push(target);
+            }
+        }
+        Self(links, PhantomData)
+    }
+
+    /// Iterates over all entities linked by this entity.
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.iter().copied()
+    }
+
+    /// Returns `true` if the given `entity` is linked by this entity.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.0.contains(&entity)
+    }
+
+    /// Returns the number of entities linked by this entity.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if this entity is not linked to any entities.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn on_insert(mut world: DeferredWorld, ctx: HookContext) {
+        let source = ctx.entity;
+        for target in world
+            .get::<Self>(source)
+            .unwrap()
+            .iter()
+            .collect::<Vec<_>>()
+        {
+            world
+                .commands()
+                .queue(move |world: &mut World| link_target::<M>(world, source, target));
+        }
+    }
+
+    fn on_discard(mut world: DeferredWorld, ctx: HookContext) {
+        let source = ctx.entity;
+        for target in world
+            .get::<Self>(source)
+            .unwrap()
+            .iter()
+            .collect::<Vec<_>>()
+        {
+            let Some(mut linked_by) = world.get_mut::<LinkedBy<M>>(target) else {
+                continue;
+            };
+
+            linked_by.0.retain(|&entity| entity != source);
+            if linked_by.0.is_empty() {
+                world
+                    .commands()
+                    .queue(move |world: &mut World| remove_empty_linked_by::<M>(world, target));
+            }
+        }
+    }
+}
+
+/// A [`Component`] which contains all the entities which are linked to an [`Entity`] via [`Links<M>`].
+///
+/// This component is managed automatically and may not be constructed manually.
+/// See [`Links<M>`] for more details.
+#[derive(Component)]
+#[component(on_discard = Self::on_discard)]
+pub struct LinkedBy<M: Static>(Vec<Entity>, PhantomData<M>);
+
+impl<M: Static> LinkedBy<M> {
+    /// Iterates over all entities which link to this entity.
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.iter().copied()
+    }
+
+    /// Returns `true` if the given `entity` links to this entity.
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.0.contains(&entity)
+    }
+
+    /// Returns the number of entities which link to this entity.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if no entities link to this entity.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn on_discard(mut world: DeferredWorld, ctx: HookContext) {
+        let target = ctx.entity;
+        for source in world
+            .get::<Self>(target)
+            .unwrap()
+            .iter()
+            .collect::<Vec<_>>()
+        {
+            let Some(mut links) = world.get_mut::<Links<M>>(source) else {
+                continue;
+            };
+
+            links.0.retain(|&entity| entity != target);
+            if links.0.is_empty() {
+                world
+                    .commands()
+                    .queue(move |world: &mut World| remove_empty_links::<M>(world, source));
+            }
+        }
+    }
+}
+
+/// Trait used to add or remove [`Links<M>`] between entities.
+///
+/// See [`Links<M>`] for more details and examples.
+pub trait LinkEntities {
+    /// Links this entity to the given `target` entity.
+    ///
+    /// This does nothing if the entities are already linked.
+    fn link<M: Static>(&mut self, target: Entity) -> &mut Self;
+
+    /// Removes the link from this entity to the given `target` entity, if it exists.
+    fn unlink<M: Static>(&mut self, target: Entity) -> &mut Self;
+}
+
+impl LinkEntities for EntityWorldMut<'_> {
+    fn link<M: Static>(&mut self, target: Entity) -> &mut Self {
+        let source = self.id();
+        if let Some(mut links) = self.get_mut::<Links<M>>() {
+            if !links.contains(target) {
+                links.0.push(target);
+                self.world_scope(|world| link_target::<M>(world, source, target));
+            }
+        } else {
+            self.insert(Links::<M>::new([target]));
+        }
+        self
+    }
+
+    fn unlink<M: Static>(&mut self, target: Entity) -> &mut Self {
+        let source = self.id();
+        let Some(mut links) = self.get_mut::<Links<M>>() else {
+            return self;
+        };
+
+        if !links.contains(target) {
+            return self;
+        }
+
+        links.0.retain(|&entity| entity != target);
+        if links.is_empty() {
+            self.remove::<Links<M>>();
+        }
+
+        self.world_scope(|world| {
+            let Ok(mut target) = world.get_entity_mut(target) else {
+                return;
+            };
+
+            if let Some(mut linked_by) = target.get_mut::<LinkedBy<M>>() {
+                linked_by.0.retain(|&entity| entity != source);
+                if linked_by.is_empty() {
+                    target.remove::<LinkedBy<M>>();
+                }
+            }
+        });
+        self
+    }
+}
+
+impl LinkEntities for EntityCommands<'_> {
+    fn link<M: Static>(&mut self, target: Entity) -> &mut Self {
+        self.queue(move |mut entity: EntityWorldMut| {
+            entity.link::<M>(target);
+        })
+    }
+
+    fn unlink<M: Static>(&mut self, target: Entity) -> &mut Self {
+        self.queue(move |mut entity: EntityWorldMut| {
+            entity.unlink::<M>(target);
+        })
+    }
+}
+
+fn link_target<M: Static>(world: &mut World, source: Entity, target: Entity) {
+    let Some(links) = world.get::<Links<M>>(source) else {
+        return;
+    };
+
+    if !links.contains(target) {
+        return;
+    }
+
+    let invalid = if source == target {
+        warn!(
+            "{}({target:?}) on entity {source:?} links to itself; the invalid link has been removed",
+            crate::get_short_type_name::<Links<M>>()
+        );
+        true
+    } else if let Ok(mut target_entity) = world.get_entity_mut(target) {
+        if let Some(mut linked_by) = target_entity.get_mut::<LinkedBy<M>>() {
+            if !linked_by.contains(source) {
+                linked_by.0.push(source);
+            }
+        } else {
+            target_entity.insert(LinkedBy::<M>(vec![source], PhantomData));
+        }
+        false
+    } else {
+        warn!(
+            "{}({target:?}) on entity {source:?} links to an entity that does not exist; the invalid link has been removed",
+            crate::get_short_type_name::<Links<M>>()
+        );
+        true
+    };
+
+    if invalid {
+        world.entity_mut(source).unlink::<M>(target);
+    }
+}
+
+fn remove_empty_links<M: Static>(world: &mut World, entity: Entity) {
+    let Ok(mut entity) = world.get_entity_mut(entity) else {
+        return;
+    };
+
+    // Links may have been re-inserted since this command was queued
+    if entity.get::<Links<M>>().is_some_and(Links::is_empty) {
+        entity.remove::<Links<M>>();
+    }
+}
+
+fn remove_empty_linked_by<M: Static>(world: &mut World, entity: Entity) {
+    let Ok(mut entity) = world.get_entity_mut(entity) else {
+        return;
+    };
+
+    if entity.get::<LinkedBy<M>>().is_some_and(LinkedBy::is_empty) {
+        entity.remove::<LinkedBy<M>>();
+    }
+}
+
 /// A convenient macro for defining a pair of [`Relationship`] and [`RelationshipTarget`] component.
 ///
 /// ```rust
@@ -241,40 +607,194 @@ impl<F: Static + FnOnce() -> T, T: MergeComponent> From<F> for MergeWith<T, F> {
 ///     .is_some_and(|Friends(friends)| friends[0] == b));
 /// ```
 ///
+/// Both structs may also have named fields, generic parameters, and additional data.
+/// If a struct has more than one field, its [`Entity`](bevy_ecs::entity::Entity) (or collection) field must be
+/// marked with `#[relationship]`. Any [`RelationshipSourceCollection`] may be used as the target collection:
+///
+/// ```rust
+/// use std::marker::PhantomData;
+///
+/// use bevy::prelude::*;
+/// use bevy::ecs::entity::EntityIndexSet;
+/// use moonshine_util::prelude::*;
+///
+/// relationship! {
+///     #[derive(Component)]
+///     pub struct Slots<T: Static> {
+///         #[relationship]
+///         children: EntityIndexSet,
+///         marker: PhantomData<T>,
+///     } -> {
+///         #[derive(Component)]
+///         pub struct ChildOfSlot<T: Static> {
+///             #[relationship]
+///             pub parent: Entity,
+///             pub slot: u8,
+///             pub marker: PhantomData<T>,
+///         }
+///     }
+/// }
+///
+/// struct Weapon;
+///
+/// let mut w = World::new();
+/// let a = w.spawn_empty().id();
+/// let b = w
+///     .spawn(ChildOfSlot::<Weapon> { parent: a, slot: 2, marker: PhantomData })
+///     .id();
+///
+/// assert!(w
+///     .get::<Slots<Weapon>>(a)
+///     .is_some_and(|slots| slots.children.contains(&b)));
+/// ```
+///
+/// Generic parameters may each have at most one trait bound.
+///
+/// # Reflection
+///
+/// The `reflect` option derives [`Reflect`](bevy_reflect::Reflect) for both types.
+/// Both types may then be registered as reflected components at once using [`register_relationship`]:
+///
+/// ```rust
+/// use bevy::app::App;
+/// use bevy::ecs::component::Component;
+/// use bevy::ecs::entity::Entity;
+/// use bevy::ecs::reflect::{AppTypeRegistry, ReflectComponent};
+/// use moonshine_util::relationship;
+/// use moonshine_util::reflect::register_relationship;
+///
+/// relationship! {
+///     #[derive(Component)]
+///     pub struct Owner(Vec<Entity>) -> [linked_spawn, reflect] {
+///         #[derive(Component)]
+///         pub struct OwnedBy(pub Entity)
+///     }
+/// }
+///
+/// let mut app = App::new();
+/// app.add_plugins(register_relationship::<OwnedBy>);
+///
+/// let registry = app.world().resource::<AppTypeRegistry>().read();
+/// assert!(registry.get_type_data::<ReflectComponent>(std::any::TypeId::of::<Owner>()).is_some());
+/// assert!(registry.get_type_data::<ReflectComponent>(std::any::TypeId::of::<OwnedBy>()).is_some());
+/// ```
+///
+/// Generic parameters do not need a [`TypePath`](bevy_reflect::TypePath) bound in the declaration, since the derived
+/// implementations add it implicitly. However, any type argument must implement `TypePath` to be registered,
+/// and [`PhantomData`](std::marker::PhantomData) fields must be marked with `#[reflect(ignore)]`.
+///
 /// [`Relationship`]: bevy_ecs::relationship::Relationship
 /// [`RelationshipTarget`]: bevy_ecs::relationship::RelationshipTarget
+/// [`RelationshipSourceCollection`]: bevy_ecs::relationship::RelationshipSourceCollection
+/// [`register_relationship`]: crate::reflect::register_relationship
 #[macro_export]
 macro_rules! relationship {
     {
         $(#[$target_attr:meta])*
-        $target_vis:vis struct $target:ident($(#[$target_inner_attr:meta])* $target_inner_vis:vis $target_inner:ty)
-        -> {
-            $(#[$source_attr:meta])*
-            $source_vis:vis struct $source:ident($(#[$source_inner_attr:meta])* $source_inner_vis:vis $source_inner:ty)
+        $target_vis:vis struct $target:ident $(<$($target_param:ident $(: $target_bound:path)?),* $(,)?>)?
+        ($($target_body:tt)*)
+        -> $([$($options:ident),* $(,)?])? {
+            $($source:tt)*
         }
     } => {
-        relationship! {
-            $(#[$target_attr])* $target_vis struct $target($target_inner_vis $target_inner) -> [] {
-                $(#[$source_attr])* $source_vis struct $source($source_inner_vis $source_inner)
-            }
+        $crate::relationship! {
+            @options [] [] [$($($options),*)?]
+            [$(#[$target_attr])*]
+            [$target_vis struct $target $(<$($target_param $(: $target_bound)?),*>)? ($($target_body)*);]
+            [$target $(<$($target_param),*>)?]
+            $($source)*
         }
     };
 
     {
         $(#[$target_attr:meta])*
-        $target_vis:vis struct $target:ident($(#[$target_inner_attr:meta])* $target_inner_vis:vis $target_inner:ty)
-        -> [$($options:expr),*] {
-            $(#[$source_attr:meta])*
-            $source_vis:vis struct $source:ident($(#[$source_inner_attr:meta])* $source_inner_vis:vis $source_inner:ty)
+        $target_vis:vis struct $target:ident $(<$($target_param:ident $(: $target_bound:path)?),* $(,)?>)?
+        {$($target_body:tt)*}
+        -> $([$($options:ident),* $(,)?])? {
+            $($source:tt)*
+        }
+    } => {
+        $crate::relationship! {
+            @options [] [] [$($($options),*)?]
+            [$(#[$target_attr])*]
+            [$target_vis struct $target $(<$($target_param $(: $target_bound)?),*>)? {$($target_body)*}]
+            [$target $(<$($target_param),*>)?]
+            $($source)*
+        }
+    };
+
+    {
+        @options [$($options:ident)*] [$($reflect:tt)*] [reflect $(, $($rest:ident),*)?]
+        $($tail:tt)*
+    } => {
+        $crate::relationship! {
+            @options [$($options)*] [#[derive($crate::__private::Reflect)]] [$($($rest),*)?]
+            $($tail)*
         }
+    };
+
+    {
+        @options [$($options:ident)*] [$($reflect:tt)*] [$option:ident $(, $($rest:ident),*)?]
+        $($tail:tt)*
     } => {
-        $(#[$target_attr])*
-        #[relationship_target(relationship = $source, $($options),*)]
-        $target_vis struct $target($(#[$target_inner_attr])* $target_inner_vis $target_inner);
+        $crate::relationship! {
+            @options [$($options)* $option] [$($reflect)*] [$($($rest),*)?]
+            $($tail)*
+        }
+    };
+
+    {
+        @options [$($options:ident)*] [$($reflect:tt)*] []
+        $($tail:tt)*
+    } => {
+        $crate::relationship! {
+            @source [$($options)*] [$($reflect)*]
+            $($tail)*
+        }
+    };
+
+    {
+        @source
+        [$($options:ident)*]
+        [$($reflect:tt)*]
+        [$($target_attr:tt)*]
+        [$($target_item:tt)*]
+        [$($target_type:tt)*]
+        $(#[$source_attr:meta])*
+        $source_vis:vis struct $source:ident $(<$($source_param:ident $(: $source_bound:path)?),* $(,)?>)?
+        ($($source_body:tt)*)
+    } => {
+        $($target_attr)*
+        $($reflect)*
+        #[relationship_target(relationship = $source $(<$($source_param),*>)? $(, $options)*)]
+        $($target_item)*
 
         $(#[$source_attr])*
-        #[relationship(relationship_target = $target)]
-        $source_vis struct $source($(#[$source_inner_attr])* $source_inner_vis $source_inner);
+        $($reflect)*
+        #[relationship(relationship_target = $($target_type)*)]
+        $source_vis struct $source $(<$($source_param $(: $source_bound)?),*>)? ($($source_body)*);
+    };
+
+    {
+        @source
+        [$($options:ident)*]
+        [$($reflect:tt)*]
+        [$($target_attr:tt)*]
+        [$($target_item:tt)*]
+        [$($target_type:tt)*]
+        $(#[$source_attr:meta])*
+        $source_vis:vis struct $source:ident $(<$($source_param:ident $(: $source_bound:path)?),* $(,)?>)?
+        {$($source_body:tt)*}
+    } => {
+        $($target_attr)*
+        $($reflect)*
+        #[relationship_target(relationship = $source $(<$($source_param),*>)? $(, $options)*)]
+        $($target_item)*
+
+        $(#[$source_attr])*
+        $($reflect)*
+        #[relationship(relationship_target = $($target_type)*)]
+        $source_vis struct $source $(<$($source_param $(: $source_bound)?),*>)? {$($source_body)*}
     };
 }
 
@@ -308,6 +828,43 @@ fn test_merge_component() {
     assert_eq!(v, 3);
 }
 
+#[test]
+fn test_merge_bundle() {
+    #[derive(Component, Default)]
+    struct N(usize);
+
+    impl MergeComponent for N {
+        fn merge(&mut self, other: Self) {
+            self.0 += other.0;
+        }
+    }
+
+    #[derive(Component, Default)]
+    struct M(usize);
+
+    impl MergeComponent for M {
+        fn merge(&mut self, other: Self) {
+            self.0 += other.0;
+        }
+    }
+
+    #[derive(Component, Default)]
+    #[require(MergeFrom<Self, (N, M)> = (N(1), M(1)))]
+    struct A;
+
+    #[derive(Component, Default)]
+    #[require(A, MergeFrom<Self, (N, M)> = (N(2), M(2)))]
+    struct B;
+
+    let mut w = World::new();
+    let e = w.spawn((B, M(3)));
+    let &N(n) = e.get().unwrap();
+    let &M(m) = e.get().unwrap();
+
+    assert_eq!(n, 3);
+    assert_eq!(m, 6);
+}
+
 #[test]
 fn test_relationship_linked_spawn() {
     relationship! {
@@ -327,3 +884,134 @@ fn test_relationship_linked_spawn() {
     w.entity_mut(a).despawn();
     assert!(!w.entities().contains(b));
 }
+
+#[test]
+fn test_relationship_reflect_generic() {
+    use bevy_ecs::reflect::{AppTypeRegistry, ReflectComponent};
+    use bevy_reflect::TypePath;
+
+    relationship! {
+        #[derive(Component)]
+        pub struct Slots<T: Static> {
+            #[relationship]
+            children: Vec<Entity>,
+            #[reflect(ignore)]
+            marker: PhantomData<T>,
+        } -> [reflect] {
+            #[derive(Component)]
+            pub struct ChildOfSlot<T: Static> {
+                #[relationship]
+                parent: Entity,
+                #[reflect(ignore)]
+                marker: PhantomData<T>,
+            }
+        }
+    }
+
+    #[derive(TypePath)]
+    struct Weapon;
+
+    let mut app = bevy_app::App::new();
+    app.add_plugins(crate::reflect::register_relationship::<ChildOfSlot<Weapon>>);
+
+    let registry = app.world().resource::<AppTypeRegistry>().read();
+    assert!(registry
+        .get_type_data::<ReflectComponent>(std::any::TypeId::of::<Slots<Weapon>>())
+        .is_some());
+    assert!(registry
+        .get_type_data::<ReflectComponent>(std::any::TypeId::of::<ChildOfSlot<Weapon>>())
+        .is_some());
+}
+
+#[test]
+fn test_relationship_named_fields() {
+    use bevy_ecs::entity::EntityIndexSet;
+
+    relationship! {
+        #[derive(Component)]
+        pub struct Slots {
+            #[relationship]
+            children: EntityIndexSet,
+            count: usize,
+        } -> [linked_spawn] {
+            #[derive(Component)]
+            pub struct ChildOfSlot {
+                #[relationship]
+                pub parent: Entity,
+                pub slot: u8,
+            }
+        }
+    }
+
+    let mut w = World::new();
+    let a = w.spawn_empty().id();
+    let b = w.spawn(ChildOfSlot { parent: a, slot: 1 }).id();
+    let c = w.spawn(ChildOfSlot { parent: a, slot: 2 }).id();
+
+    let slots = w.get::<Slots>(a).unwrap();
+    assert_eq!(slots.count, 0);
+    assert_eq!(slots.children.iter().copied().collect::<Vec<_>>(), [b, c]);
+    assert_eq!(w.get::<ChildOfSlot>(c).unwrap().slot, 2);
+
+    w.entity_mut(a).despawn();
+    assert!(!w.entities().contains(b));
+    assert!(!w.entities().contains(c));
+}
+
+#[test]
+fn test_relationship_generic() {
+    use std::collections::BTreeSet;
+
+    use bevy_ecs::relationship::RelationshipTarget;
+
+    relationship! {
+        #[derive(Component)]
+        pub struct Likes<T: Static>(#[relationship] BTreeSet<Entity>, PhantomData<T>) -> {
+            #[derive(Component)]
+            pub struct LikedBy<T: Static>(#[relationship] pub Entity, pub PhantomData<T>)
+        }
+    }
+
+    struct Food;
+
+    let mut w = World::new();
+    let a = w.spawn_empty().id();
+    let b = w.spawn(LikedBy::<Food>(a, PhantomData)).id();
+
+    assert_eq!(
+        w.get::<Likes<Food>>(a).unwrap().iter().collect::<Vec<_>>(),
+        [b]
+    );
+    assert!(w.get::<Likes<()>>(a).is_none());
+}
+
+#[test]
+fn test_relationship_reflect() {
+    use bevy_ecs::reflect::{AppTypeRegistry, ReflectComponent};
+
+    relationship! {
+        #[derive(Component)]
+        pub struct Owner(Vec<Entity>) -> [reflect, linked_spawn] {
+            #[derive(Component)]
+            pub struct OwnedBy(pub Entity)
+        }
+    }
+
+    let mut app = bevy_app::App::new();
+    app.add_plugins(crate::reflect::register_relationship::<OwnedBy>);
+
+    let registry = app.world().resource::<AppTypeRegistry>().read();
+    assert!(registry
+        .get_type_data::<ReflectComponent>(std::any::TypeId::of::<Owner>())
+        .is_some());
+    assert!(registry
+        .get_type_data::<ReflectComponent>(std::any::TypeId::of::<OwnedBy>())
+        .is_some());
+    drop(registry);
+
+    let w = app.world_mut();
+    let a = w.spawn_empty().id();
+    let b = w.spawn(OwnedBy(a)).id();
+    w.entity_mut(a).despawn();
+    assert!(!w.entities().contains(b));
+}
@@ -1,20 +1,40 @@
 //! Utilities related to relationship hierarchy traversal.
 
+use std::any::TypeId;
 use std::collections::VecDeque;
+use std::fmt;
 use std::marker::PhantomData;
+use std::ops::Deref;
 
+use bevy_app::prelude::*;
+use bevy_ecs::component::ComponentId;
+use bevy_ecs::entity::{EntityCloner, EntityHashMap};
 use bevy_ecs::prelude::*;
-use bevy_ecs::relationship::{Relationship, SourceIter};
-use bevy_ecs::system::SystemParam;
+use bevy_ecs::query::{IterQueryData, QueryData, QueryFilter, ROQueryItem, ReadOnlyQueryData};
+use bevy_ecs::relationship::{
+    OrderedRelationshipSourceCollection, Relationship, RelationshipTarget, SourceIter,
+};
+use bevy_ecs::system::{EntityCommand, SystemParam};
+use bevy_log::prelude::*;
+use bevy_platform::collections::{HashMap, HashSet};
+use bevy_tasks::{ComputeTaskPool, TaskPool};
+
+use crate::component::{LinkedBy, Links};
+use crate::{get_short_name, get_short_type_name, Static};
 
 /// A [`SystemParam`] for ergonomic [`Entity`] hierarchy traversal.
+///
+/// An optional [`QueryData`] parameter `D` may be given to access data of each entity during traversal.
+/// See [`children_with_data`](HierarchyQuery::children_with_data) and
+/// [`propagate_mut`](HierarchyQuery::propagate_mut) for more information.
 #[derive(SystemParam)]
-pub struct HierarchyQuery<'w, 's, R: Relationship = ChildOf> {
+pub struct HierarchyQuery<'w, 's, R: Relationship = ChildOf, D: QueryData + 'static = ()> {
     parent: Query<'w, 's, &'static R>,
     children: Query<'w, 's, &'static <R as Relationship>::RelationshipTarget>,
+    data: Query<'w, 's, D>,
 }
 
-impl<R: Relationship> HierarchyQuery<'_, '_, R> {
+impl<R: Relationship, D: QueryData + 'static> HierarchyQuery<'_, '_, R, D> {
     /// Returns the parent of the given entity, if it has one.
     ///
     /// See [`ChildOf`] for more information.
@@ -33,6 +53,95 @@ impl<R: Relationship> HierarchyQuery<'_, '_, R> {
             .flat_map(|children| children.iter())
     }
 
+    /// Iterates over the siblings of the given entity, excluding the entity itself.
+    pub fn siblings(&self, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+        self.parent(entity)
+            .into_iter()
+            .flat_map(|parent| self.children(parent))
+            .filter(move |&sibling| sibling != entity)
+    }
+
+    /// Returns the index of the given entity among the children of its parent, if it has one.
+    pub fn sibling_index(&self, entity: Entity) -> Option<usize> {
+        let parent = self.parent(entity)?;
+        self.children(parent).position(|child| child == entity)
+    }
+
+    /// Returns the sibling which comes after the given entity among the children of its parent, if any.
+    pub fn next_sibling(&self, entity: Entity) -> Option<Entity> {
+        let parent = self.parent(entity)?;
+        let mut children = self.children(parent);
+        children.find(|&child| child == entity)?;
+        children.next()
+    }
+
+    /// Returns the sibling which comes before the given entity among the children of its parent, if any.
+    pub fn prev_sibling(&self, entity: Entity) -> Option<Entity> {
+        let parent = self.parent(entity)?;
+        let mut prev = None;
+        for child in self.children(parent) {
+            if child == entity {
+                return prev;
+            }
+            prev = Some(child);
+        }
+        None
+    }
+
+    /// Returns the [`Relationship`] component of the given entity, if it has one.
+    ///
+    /// # Usage
+    ///
+    /// A [`Relationship`] component may contain additional data which describes the edge between the entity
+    /// and its parent (see [`relationship!`](crate::relationship)). Because this data is stored on the relationship
+    /// itself, it is always consistent with the hierarchy, even if the entity is reparented or despawned.
+    pub fn edge(&self, entity: Entity) -> Option<&R> {
+        self.parent.get(entity).ok()
+    }
+
+    /// Returns the [`Relationship`] component of the given `child` if it is a child of the given `parent`.
+    ///
+    /// See [`edge`](HierarchyQuery::edge) for more information.
+    pub fn child_edge(&self, parent: Entity, child: Entity) -> Option<&R> {
+        self.edge(child).filter(|edge| edge.get() == parent)
+    }
+
+    /// Iterates over the children of the given entity along with their [`Relationship`] components.
+    ///
+    /// See [`edge`](HierarchyQuery::edge) for more information.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy::prelude::*;
+    /// use moonshine_util::prelude::*;
+    /// use moonshine_util::hierarchy::HierarchyQuery;
+    ///
+    /// relationship! {
+    ///     #[derive(Component)]
+    ///     pub struct Slots(Vec<Entity>) -> {
+    ///         #[derive(Component)]
+    ///         pub struct ChildOfSlot {
+    ///             #[relationship]
+    ///             pub parent: Entity,
+    ///             pub slot: u8,
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// fn slots(entity: In<Entity>, hierarchy: HierarchyQuery<ChildOfSlot>) -> Vec<(Entity, u8)> {
+    ///     hierarchy
+    ///         .child_edges(*entity)
+    ///         .map(|(child, edge)| (child, edge.slot))
+    ///         .collect()
+    /// }
+    /// # bevy_ecs::system::assert_is_system(slots);
+    /// ```
+    pub fn child_edges(&self, entity: Entity) -> impl Iterator<Item = (Entity, &R)> + '_ {
+        self.children(entity)
+            .filter_map(|child| self.edge(child).map(|edge| (child, edge)))
+    }
+
     /// Iterates over the ancestors of the given `entity`.
     ///
     /// See [`Query::iter_ancestors`] for more information.
@@ -40,6 +149,73 @@ impl<R: Relationship> HierarchyQuery<'_, '_, R> {
         self.parent.iter_ancestors(entity)
     }
 
+    /// Returns the root of the hierarchy which contains the given `entity`.
+    ///
+    /// If the given entity has no parent, it is returned as the root.
+    ///
+    /// This method, and all other ancestry queries, stop at the first repeated ancestor.
+    /// If the hierarchy is cyclic, the last ancestor before the cycle repeats is returned as the root.
+    pub fn root(&self, entity: Entity) -> Entity {
+        self.ancestor_path(entity).last().unwrap_or(entity)
+    }
+
+    /// Returns the number of ancestors of the given `entity`.
+    ///
+    /// The depth of a root entity is zero.
+    pub fn depth(&self, entity: Entity) -> usize {
+        self.ancestor_path(entity).count() - 1
+    }
+
+    /// Returns `true` if the given `ancestor` is an ancestor of the given `entity`.
+    pub fn is_ancestor_of(&self, ancestor: Entity, entity: Entity) -> bool {
+        self.ancestor_path(entity).skip(1).any(|e| e == ancestor)
+    }
+
+    /// Returns `true` if the given `descendant` is a descendant of the given `entity`.
+    pub fn is_descendant_of(&self, descendant: Entity, entity: Entity) -> bool {
+        self.is_ancestor_of(entity, descendant)
+    }
+
+    /// Returns the lowest common ancestor of the given entities, if they are in the same hierarchy.
+    ///
+    /// If one entity is an ancestor of the other, it is returned as the common ancestor.
+    pub fn lowest_common_ancestor(&self, a: Entity, b: Entity) -> Option<Entity> {
+        let path: HashSet<Entity> = self.ancestor_path(a).collect();
+        self.ancestor_path(b).find(|entity| path.contains(entity))
+    }
+
+    /// Returns the path between the given entities through their lowest common ancestor.
+    ///
+    /// The path starts at `from`, goes up the hierarchy to the common ancestor, and down to `to`.
+    /// It includes `from`, `to`, and the common ancestor.
+    ///
+    /// Returns [`None`] if the entities are not in the same hierarchy.
+    pub fn path_between(&self, from: Entity, to: Entity) -> Option<Vec<Entity>> {
+        let ancestor = self.lowest_common_ancestor(from, to)?;
+
+        let mut path: Vec<Entity> = self
+            .ancestor_path(from)
+            .take_while(|&entity| entity != ancestor)
+            .collect();
+        path.push(ancestor);
+
+        let down: Vec<Entity> = self
+            .ancestor_path(to)
+            .take_while(|&entity| entity != ancestor)
+            .collect();
+        path.extend(down.into_iter().rev());
+
+        Some(path)
+    }
+
+    /// Iterates over the given `entity` and its ancestors, stopping before the first repeated entity.
+    fn ancestor_path(&self, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+        let mut visited = HashSet::new();
+        std::iter::once(entity)
+            .chain(self.ancestors(entity))
+            .take_while(move |&entity| visited.insert(entity))
+    }
+
     /// Iterates over the descendants of the given `entity` in a breadth-first order.
     ///
     /// See [`Query::iter_descendants`] for more information.
@@ -56,281 +232,4319 @@ impl<R: Relationship> HierarchyQuery<'_, '_, R> {
     {
         self.children.iter_descendants_depth_first(entity)
     }
-}
 
-/// Iterator for breadth-first traversal of descendants.
-pub struct WorldDescendantsWideIter<'w, R: Relationship = ChildOf> {
-    world: &'w World,
-    queue: VecDeque<Entity>,
-    _marker: PhantomData<R>,
+    /// Iterates over the descendants of the given `entity` in breadth-first order,
+    /// returning a [`TraversalItem`] for each descendant.
+    ///
+    /// If `max_depth` is given, descendants deeper than it are never visited.
+    /// The children of the given `entity` have a depth of `1`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy::prelude::*;
+    /// use moonshine_util::hierarchy::HierarchyQuery;
+    ///
+    /// fn print_tree(entity: In<Entity>, hierarchy: HierarchyQuery) {
+    ///     for item in hierarchy.traverse_wide(*entity, None) {
+    ///         println!("{}{}", "  ".repeat(item.depth), item.entity);
+    ///     }
+    /// }
+    /// # bevy_ecs::system::assert_is_system(print_tree);
+    /// ```
+    pub fn traverse_wide(
+        &self,
+        entity: Entity,
+        max_depth: Option<usize>,
+    ) -> impl Iterator<Item = TraversalItem> + '_ {
+        let mut queue: VecDeque<TraversalItem> =
+            TraversalItem::children_of(entity, 0, self.children(entity))
+                .filter(|item| max_depth.is_none_or(|max_depth| item.depth <= max_depth))
+                .collect();
+        std::iter::from_fn(move || {
+            let current = queue.pop_front()?;
+
+            if max_depth.is_none_or(|max_depth| current.depth < max_depth) {
+                queue.extend(TraversalItem::children_of(
+                    current.entity,
+                    current.depth,
+                    self.children(current.entity),
+                ));
+            }
+
+            Some(current)
+        })
+    }
+
+    /// Iterates over the descendants of the given `entity` in depth-first order,
+    /// returning a [`TraversalItem`] for each descendant.
+    ///
+    /// See [`traverse_wide`](HierarchyQuery::traverse_wide) for more information.
+    pub fn traverse_deep(
+        &self,
+        entity: Entity,
+        max_depth: Option<usize>,
+    ) -> impl Iterator<Item = TraversalItem> + '_ {
+        let children: Vec<TraversalItem> =
+            TraversalItem::children_of(entity, 0, self.children(entity))
+                .filter(|item| max_depth.is_none_or(|max_depth| item.depth <= max_depth))
+                .collect();
+        let mut stack: Vec<TraversalItem> = children.into_iter().rev().collect();
+        std::iter::from_fn(move || {
+            let current = stack.pop()?;
+
+            if max_depth.is_none_or(|max_depth| current.depth < max_depth) {
+                let children: Vec<TraversalItem> = TraversalItem::children_of(
+                    current.entity,
+                    current.depth,
+                    self.children(current.entity),
+                )
+                .collect();
+                stack.extend(children.into_iter().rev());
+            }
+
+            Some(current)
+        })
+    }
+
+    /// Visits the given `entity` and all its descendants in depth-first order using the given [`HierarchyVisitor`].
+    ///
+    /// Returns `false` if the traversal was stopped by the visitor.
+    ///
+    /// See [`HierarchyVisitor`] for more information.
+    pub fn visit_subtree(&self, entity: Entity, visitor: &mut impl HierarchyVisitor) -> bool {
+        visit_subtree_with(entity, |entity| self.children(entity).collect(), visitor)
+    }
+
+    /// Iterates over the descendants of the given `entity` in breadth-first order,
+    /// skipping entities and subtrees based on the given predicates.
+    ///
+    /// Only the descendants for which `visit` returns `true` are returned.
+    /// The children of any descendant for which `prune` returns `true` are never visited.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy::prelude::*;
+    /// use moonshine_util::hierarchy::HierarchyQuery;
+    ///
+    /// #[derive(Component)]
+    /// struct Hidden;
+    ///
+    /// fn visible_descendants(
+    ///     entity: In<Entity>,
+    ///     hierarchy: HierarchyQuery,
+    ///     hidden: Query<(), With<Hidden>>,
+    /// ) -> Vec<Entity> {
+    ///     hierarchy
+    ///         .descendants_wide_filtered(*entity, |e| !hidden.contains(e), |e| hidden.contains(e))
+    ///         .collect()
+    /// }
+    /// # bevy_ecs::system::assert_is_system(visible_descendants);
+    /// ```
+    pub fn descendants_wide_filtered<'a>(
+        &'a self,
+        entity: Entity,
+        mut visit: impl 'a + FnMut(Entity) -> bool,
+        mut prune: impl 'a + FnMut(Entity) -> bool,
+    ) -> impl Iterator<Item = Entity> + 'a {
+        let mut queue: VecDeque<Entity> = self.children(entity).collect();
+        std::iter::from_fn(move || loop {
+            let current = queue.pop_front()?;
+
+            if !prune(current) {
+                queue.extend(self.children(current));
+            }
+
+            if visit(current) {
+                return Some(current);
+            }
+        })
+    }
+
+    /// Iterates over the descendants of the given `entity` in depth-first order,
+    /// skipping entities and subtrees based on the given predicates.
+    ///
+    /// See [`descendants_wide_filtered`](HierarchyQuery::descendants_wide_filtered) for more information.
+    pub fn descendants_deep_filtered<'a>(
+        &'a self,
+        entity: Entity,
+        mut visit: impl 'a + FnMut(Entity) -> bool,
+        mut prune: impl 'a + FnMut(Entity) -> bool,
+    ) -> impl Iterator<Item = Entity> + 'a {
+        let children: Vec<Entity> = self.children(entity).collect();
+        let mut stack: Vec<Entity> = children.into_iter().rev().collect();
+        std::iter::from_fn(move || loop {
+            let current = stack.pop()?;
+
+            if !prune(current) {
+                let children: Vec<Entity> = self.children(current).collect();
+                stack.extend(children.into_iter().rev());
+            }
+
+            if visit(current) {
+                return Some(current);
+            }
+        })
+    }
 }
 
-impl<'w, R: Relationship> WorldDescendantsWideIter<'w, R> {
-    /// Creates a new [`WorldDescendantsWideIter`] to iterate over all descendants of the given
-    /// [`Entity`] in breadth-first order.
-    pub fn new(world: &'w World, root: Entity) -> Self {
-        let mut queue = VecDeque::new();
+impl<'s, R: Relationship, D: QueryData + 'static> HierarchyQuery<'_, 's, R, D> {
+    /// Returns the query data of the given `entity`, if it matches.
+    pub fn get(&self, entity: Entity) -> Option<ROQueryItem<'_, 's, D>> {
+        self.data.get(entity).ok()
+    }
 
-        if let Some(children) = world.get::<R::RelationshipTarget>(root) {
-            queue.extend(children.iter());
+    /// Returns the mutable query data of the given `entity`, if it matches.
+    pub fn get_mut(&mut self, entity: Entity) -> Option<D::Item<'_, 's>> {
+        self.data.get_mut(entity).ok()
+    }
+
+    /// Returns the parent of the given `entity` along with its query data, if it matches.
+    pub fn parent_with_data(&self, entity: Entity) -> Option<(Entity, ROQueryItem<'_, 's, D>)> {
+        let parent = self.parent(entity)?;
+        self.get(parent).map(|data| (parent, data))
+    }
+
+    /// Iterates over the children of the given `entity` along with their query data.
+    ///
+    /// Children which do not match the query data are skipped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy::prelude::*;
+    /// use moonshine_util::hierarchy::HierarchyQuery;
+    ///
+    /// fn child_names(entity: In<Entity>, hierarchy: HierarchyQuery<ChildOf, &Name>) -> Vec<String> {
+    ///     hierarchy
+    ///         .children_with_data(*entity)
+    ///         .map(|(_, name)| name.to_string())
+    ///         .collect()
+    /// }
+    /// # bevy_ecs::system::assert_is_system(child_names);
+    /// ```
+    pub fn children_with_data(
+        &self,
+        entity: Entity,
+    ) -> impl Iterator<Item = (Entity, ROQueryItem<'_, 's, D>)> + '_ {
+        self.with_data(self.children(entity))
+    }
+
+    /// Iterates over the ancestors of the given `entity` along with their query data.
+    ///
+    /// Ancestors which do not match the query data are skipped.
+    pub fn ancestors_with_data(
+        &self,
+        entity: Entity,
+    ) -> impl Iterator<Item = (Entity, ROQueryItem<'_, 's, D>)> + '_ {
+        self.with_data(self.ancestors(entity))
+    }
+
+    /// Iterates over the descendants of the given `entity` in breadth-first order along with their query data.
+    ///
+    /// Descendants which do not match the query data are skipped, but their children are still visited.
+    pub fn descendants_wide_with_data(
+        &self,
+        entity: Entity,
+    ) -> impl Iterator<Item = (Entity, ROQueryItem<'_, 's, D>)> + '_ {
+        self.with_data(self.descendants_wide(entity))
+    }
+
+    /// Iterates over the descendants of the given `entity` in depth-first order along with their query data.
+    ///
+    /// See [`descendants_wide_with_data`](HierarchyQuery::descendants_wide_with_data) for more information.
+    pub fn descendants_deep_with_data<'a>(
+        &'a self,
+        entity: Entity,
+    ) -> impl Iterator<Item = (Entity, ROQueryItem<'a, 's, D>)> + 'a
+    where
+        SourceIter<'a, R::RelationshipTarget>: DoubleEndedIterator,
+    {
+        self.with_data(self.descendants_deep(entity))
+    }
+
+    /// Computes a post-order reduction of the given `entity` and all its descendants.
+    ///
+    /// The given function is called once for each entity in the subtree, after it has been called for all its children.
+    /// It receives the entity, its query data (if it matches), and the results of all its children in order.
+    /// The result of the given `entity` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy::prelude::*;
+    /// use moonshine_util::hierarchy::HierarchyQuery;
+    ///
+    /// #[derive(Component)]
+    /// struct Weight(f32);
+    ///
+    /// fn total_weight(container: In<Entity>, hierarchy: HierarchyQuery<ChildOf, &Weight>) -> f32 {
+    ///     hierarchy.fold_subtree(*container, |_, weight, children| {
+    ///         weight.map_or(0.0, |weight| weight.0) + children.iter().sum::<f32>()
+    ///     })
+    /// }
+    /// # bevy_ecs::system::assert_is_system(total_weight);
+    /// ```
+    pub fn fold_subtree<T>(
+        &self,
+        entity: Entity,
+        mut f: impl FnMut(Entity, Option<ROQueryItem<'_, 's, D>>, Vec<T>) -> T,
+    ) -> T {
+        fold_subtree_with(
+            entity,
+            |entity| self.children(entity).collect(),
+            |entity, children| f(entity, self.get(entity), children),
+        )
+    }
+
+    /// Calls the given function with the mutable query data of each descendant of the given `entity`,
+    /// one at a time, in breadth-first order.
+    ///
+    /// Descendants which do not match the query data are skipped, but their children are still visited.
+    pub fn for_each_descendant_mut(
+        &mut self,
+        entity: Entity,
+        mut f: impl FnMut(Entity, D::Item<'_, 's>),
+    ) {
+        for descendant in self.children.iter_descendants(entity) {
+            if let Ok(data) = self.data.get_mut(descendant) {
+                f(descendant, data);
+            }
+        }
+    }
+
+    /// Calls the given function with the mutable query data of each descendant of the given `entity`
+    /// and its parent, in breadth-first order.
+    ///
+    /// Because each parent is always visited before its children, this may be used to propagate values
+    /// down the hierarchy. Descendants which, or whose parents, do not match the query data are skipped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy::prelude::*;
+    /// use moonshine_util::hierarchy::HierarchyQuery;
+    ///
+    /// #[derive(Component)]
+    /// struct Visible(bool);
+    ///
+    /// fn propagate_visibility(root: In<Entity>, mut hierarchy: HierarchyQuery<ChildOf, &mut Visible>) {
+    ///     hierarchy.propagate_mut(*root, |parent, mut child| child.0 &= parent.0);
+    /// }
+    /// # bevy_ecs::system::assert_is_system(propagate_visibility);
+    /// ```
+    pub fn propagate_mut(
+        &mut self,
+        entity: Entity,
+        mut f: impl FnMut(D::Item<'_, 's>, D::Item<'_, 's>),
+    ) where
+        D: IterQueryData,
+    {
+        for descendant in self.children.iter_descendants(entity) {
+            let Ok(parent) = self.parent.get(descendant) else {
+                continue;
+            };
+            if let Ok([parent, child]) = self.data.get_many_mut([parent.get(), descendant]) {
+                f(parent, child);
+            }
+        }
+    }
+
+    /// Calls the given function with the mutable query data of each descendant of the given `entity`,
+    /// one at a time, using the [`ComputeTaskPool`] to process independent subtrees in parallel.
+    ///
+    /// The order in which descendants are visited is unspecified, except that each parent is always
+    /// visited before its children. Descendants which do not match the query data are skipped,
+    /// but their children are still visited.
+    ///
+    /// Each descendant is only visited through the entity its relationship points to, so each
+    /// descendant is visited at most once, even if the hierarchy is malformed or cyclic.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy::prelude::*;
+    /// use moonshine_util::hierarchy::HierarchyQuery;
+    ///
+    /// #[derive(Component)]
+    /// struct Particle {
+    ///     age: f32,
+    /// }
+    ///
+    /// fn update_particles(
+    ///     root: In<Entity>,
+    ///     time: Res<Time>,
+    ///     mut hierarchy: HierarchyQuery<ChildOf, &mut Particle>,
+    /// ) {
+    ///     let delta = time.delta_secs();
+    ///     hierarchy.par_for_each_descendant_mut(*root, |_, mut particle| particle.age += delta);
+    /// }
+    /// # bevy_ecs::system::assert_is_system(update_particles);
+    /// ```
+    pub fn par_for_each_descendant_mut(
+        &mut self,
+        entity: Entity,
+        f: impl Fn(Entity, D::Item<'_, 's>) + Send + Sync,
+    ) where
+        D: IterQueryData,
+    {
+        let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+        let batch_size = pool.thread_num() * 4;
+
+        // Visit the top of the hierarchy until there are enough subtrees to process in parallel.
+        let mut subtrees = self.tree_children(entity, entity);
+        while subtrees.len() < batch_size && !subtrees.is_empty() {
+            let mut next = Vec::new();
+            for &descendant in &subtrees {
+                // SAFETY: `D` is `IterQueryData`, so items of distinct entities never alias,
+                // and each descendant is visited at most once. See `visit_tree_unchecked`.
+                if let Ok(data) = unsafe { self.data.get_unchecked(descendant) } {
+                    f(descendant, data);
+                }
+                next.extend(self.tree_children(entity, descendant));
+            }
+            subtrees = next;
         }
 
+        let this = &*self;
+        let f = &f;
+        pool.scope(|scope| {
+            for subtree in subtrees {
+                // SAFETY: `D` is `IterQueryData`, so items of distinct entities never alias,
+                // and subtrees are disjoint. See `visit_tree_unchecked`.
+                scope
+                    .spawn(async move { unsafe { this.visit_tree_unchecked(entity, subtree, f) } });
+            }
+        });
+    }
+
+    /// Returns the children of the given `entity` which are only reachable through it.
+    ///
+    /// Children whose relationship does not point to the given `entity`, duplicate children,
+    /// and the traversal `root` itself are excluded.
+    fn tree_children(&self, root: Entity, entity: Entity) -> Vec<Entity> {
+        let mut children: Vec<Entity> = self
+            .children(entity)
+            .filter(|&child| child != root && self.parent(child) == Some(entity))
+            .collect();
+        children.sort_unstable();
+        children.dedup();
+        children
+    }
+
+    /// # Safety
+    ///
+    /// The caller must have exclusive access to this query, and `entity` and its descendants must not be visited
+    /// by any other call. Because every entity has at most one parent, and [`tree_children`](Self::tree_children)
+    /// only returns children of their own parent, each entity below `root` is visited at most once.
+    /// Because `D` is [`IterQueryData`], the items of distinct entities never alias.
+    unsafe fn visit_tree_unchecked(
+        &self,
+        root: Entity,
+        entity: Entity,
+        f: &(impl Fn(Entity, D::Item<'_, 's>) + Send + Sync),
+    ) where
+        D: IterQueryData,
+    {
+        let mut stack = vec![entity];
+        while let Some(current) = stack.pop() {
+            // SAFETY: Each entity is visited at most once. See above.
+            if let Ok(data) = unsafe { self.data.get_unchecked(current) } {
+                f(current, data);
+            }
+            stack.extend(self.tree_children(root, current));
+        }
+    }
+
+    fn with_data<'a>(
+        &'a self,
+        entities: impl Iterator<Item = Entity> + 'a,
+    ) -> impl Iterator<Item = (Entity, ROQueryItem<'a, 's, D>)> + 'a {
+        entities.filter_map(|entity| self.get(entity).map(|data| (entity, data)))
+    }
+}
+
+/// A [`SystemParam`] for ergonomic traversal of entities linked with [`Links<M>`].
+///
+/// # Usage
+///
+/// This is similar to [`HierarchyQuery`], except that each entity may have many parents.
+/// Like [`ChildOf`], an entity with [`Links<M>`] is considered a child of all the entities it links to.
+///
+/// Because linked entities may form cycles, all traversal methods visit each entity at most once,
+/// and never visit the given `entity` itself.
+#[derive(SystemParam)]
+pub struct LinkQuery<'w, 's, M: Static> {
+    parents: Query<'w, 's, &'static Links<M>>,
+    children: Query<'w, 's, &'static LinkedBy<M>>,
+}
+
+impl<M: Static> LinkQuery<'_, '_, M> {
+    /// Iterates over the parents of the given entity.
+    ///
+    /// These are the entities which the given entity links to.
+    pub fn parents(&self, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+        self.parents
+            .get(entity)
+            .ok()
+            .into_iter()
+            .flat_map(|parents| parents.iter())
+    }
+
+    /// Iterates over the children of the given entity.
+    ///
+    /// These are the entities which link to the given entity.
+    pub fn children(&self, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+        self.children
+            .get(entity)
+            .ok()
+            .into_iter()
+            .flat_map(|children| children.iter())
+    }
+
+    /// Iterates over the ancestors of the given `entity` in breadth-first order.
+    pub fn ancestors(&self, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+        LinkWideIter::new(entity, move |entity| self.parents(entity))
+    }
+
+    /// Iterates over the descendants of the given `entity` in breadth-first order.
+    pub fn descendants_wide(&self, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+        LinkWideIter::new(entity, move |entity| self.children(entity))
+    }
+
+    /// Iterates over the descendants of the given `entity` in depth-first order.
+    pub fn descendants_deep(&self, entity: Entity) -> impl Iterator<Item = Entity> + '_ {
+        LinkDeepIter::new(entity, move |entity| self.children(entity))
+    }
+}
+
+struct LinkWideIter<F> {
+    next: F,
+    queue: VecDeque<Entity>,
+    visited: HashSet<Entity>,
+}
+
+impl<F: FnMut(Entity) -> I, I: Iterator<Item = Entity>> LinkWideIter<F> {
+    fn new(root: Entity, mut next: F) -> Self {
+        let mut visited: HashSet<Entity> = HashSet::from_iter([root]);
+        let queue = next(root)
+            .filter(|&entity| visited.insert(entity))
+            .collect();
         Self {
-            world,
+            next,
             queue,
-            _marker: PhantomData,
+            visited,
         }
     }
 }
 
-impl<R: Relationship> Iterator for WorldDescendantsWideIter<'_, R> {
+impl<F: FnMut(Entity) -> I, I: Iterator<Item = Entity>> Iterator for LinkWideIter<F> {
     type Item = Entity;
 
     fn next(&mut self) -> Option<Self::Item> {
         let current = self.queue.pop_front()?;
 
-        if let Some(children) = self.world.get::<R::RelationshipTarget>(current) {
-            self.queue.extend(children.iter());
+        for entity in (self.next)(current) {
+            if self.visited.insert(entity) {
+                self.queue.push_back(entity);
+            }
         }
 
         Some(current)
     }
 }
 
-/// Iterator for depth-first traversal of descendants.
-pub struct WorldDescendantsDeepIter<'w, R: Relationship = ChildOf> {
-    world: &'w World,
+struct LinkDeepIter<F> {
+    next: F,
     stack: Vec<Entity>,
-    _marker: PhantomData<R>,
+    visited: HashSet<Entity>,
 }
 
-impl<'w, R: Relationship> WorldDescendantsDeepIter<'w, R> {
-    /// Creates a new [`WorldDescendantsDeepIter`] to iterate over all descendants of the given
-    /// [`Entity`] in depth-first order.
-    pub fn new(world: &'w World, root: Entity) -> Self {
-        let mut stack = Vec::new();
+impl<F: FnMut(Entity) -> I, I: Iterator<Item = Entity>> LinkDeepIter<F> {
+    fn new(root: Entity, mut next: F) -> Self {
+        let children: Vec<Entity> = next(root).collect();
+        Self {
+            next,
+            stack: children.into_iter().rev().collect(),
+            visited: HashSet::from_iter([root]),
+        }
+    }
+}
 
-        if let Some(children) = world.get::<R::RelationshipTarget>(root) {
-            let children: Vec<Entity> = children.iter().collect();
-            stack.extend(children.into_iter().rev());
+impl<F: FnMut(Entity) -> I, I: Iterator<Item = Entity>> Iterator for LinkDeepIter<F> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let current = self.stack.pop()?;
+            if !self.visited.insert(current) {
+                continue;
+            }
+
+            let children: Vec<Entity> = (self.next)(current).collect();
+            self.stack.extend(children.into_iter().rev());
+            return Some(current);
         }
+    }
+}
+
+/// Iterator for traversal of ancestors.
+pub struct WorldAncestorsIter<'w, R: Relationship = ChildOf> {
+    world: &'w World,
+    next: Option<Entity>,
+    _marker: PhantomData<R>,
+}
 
+impl<'w, R: Relationship> WorldAncestorsIter<'w, R> {
+    /// Creates a new [`WorldAncestorsIter`] to iterate over all ancestors of the given [`Entity`],
+    /// starting with its parent.
+    pub fn new(world: &'w World, entity: Entity) -> Self {
         Self {
             world,
-            stack,
+            next: world_parent::<R>(world, entity),
             _marker: PhantomData,
         }
     }
 }
 
-impl<R: Relationship> Iterator for WorldDescendantsDeepIter<'_, R> {
+impl<R: Relationship> Iterator for WorldAncestorsIter<'_, R> {
     type Item = Entity;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let current = self.stack.pop()?;
+        let current = self.next?;
+        self.next = world_parent::<R>(self.world, current);
+        Some(current)
+    }
+}
 
-        if let Some(children) = self.world.get::<R::RelationshipTarget>(current) {
-            let children: Vec<Entity> = children.iter().collect();
-            self.stack.extend(children.into_iter().rev());
-        }
+/// An error returned by [`CycleSafeIter`] when an entity is visited more than once.
+///
+/// This usually means the hierarchy contains a cycle. However, it may also be caused by an entity which is
+/// listed more than once in a [`RelationshipTarget`] collection,
+/// which also makes traversal visit it (and its descendants) more than once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HierarchyCycleError {
+    /// The entity which was visited more than once.
+    pub entity: Entity,
+}
 
-        Some(current)
+impl fmt::Display for HierarchyCycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "entity {} was visited more than once", self.entity)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use bevy_ecs::system::RunSystemOnce;
+impl std::error::Error for HierarchyCycleError {}
 
-    use super::*;
+/// Trait used to make any hierarchy iterator safe against cycles.
+///
+/// # Usage
+///
+/// In a valid hierarchy, no entity is ever visited twice during traversal.
+/// However, relationships are not guaranteed to be acyclic, and traversing a cyclic hierarchy never terminates.
+///
+/// This trait may be used with any [`Iterator`] of entities, such as [`HierarchyQuery::ancestors`],
+/// [`HierarchyQuery::descendants_wide`], or [`WorldAncestorsIter`], to keep track of all visited entities.
+/// If an entity is visited twice, the iterator returns a [`HierarchyCycleError`] and ends.
+///
+/// Note that visiting an entity twice does not necessarily mean the hierarchy is cyclic.
+/// See [`HierarchyCycleError`] for details.
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::hierarchy::{CycleSafe, HierarchyCycleError, WorldAncestorsIter};
+///
+/// let mut world = World::new();
+/// let a = world.spawn_empty().id();
+/// let b = world.spawn(ChildOf(a)).id();
+/// let c = world.spawn(ChildOf(b)).id();
+///
+/// let ancestors: Result<Vec<Entity>, _> = WorldAncestorsIter::<ChildOf>::new(&world, c)
+///     .cycle_safe()
+///     .collect();
+/// assert_eq!(ancestors, Ok(vec![b, a]));
+///
+/// // Oops!
+/// world.entity_mut(a).insert(ChildOf(c));
+///
+/// let ancestors: Result<Vec<Entity>, _> = WorldAncestorsIter::<ChildOf>::new(&world, c)
+///     .cycle_safe()
+///     .collect();
+/// assert_eq!(ancestors, Err(HierarchyCycleError { entity: b }));
+/// ```
+pub trait CycleSafe: Iterator<Item = Entity> + Sized {
+    /// Converts this iterator into a [`CycleSafeIter`].
+    fn cycle_safe(self) -> CycleSafeIter<Self> {
+        CycleSafeIter {
+            iter: Some(self),
+            visited: HashSet::new(),
+        }
+    }
+}
 
-    #[test]
-    fn parent_child() {
-        let mut w = World::new();
-        let e = w
-            .spawn_empty()
-            .with_children(|parent| {
-                parent.spawn_empty();
-            })
-            .id();
+impl<I: Iterator<Item = Entity>> CycleSafe for I {}
 
-        let pass = w.run_system_once(move |q: HierarchyQuery| {
-            assert_eq!(q.children(e).count(), 1);
-            true
-        });
+/// An iterator which returns a [`HierarchyCycleError`] if any entity is visited more than once.
+///
+/// See [`CycleSafe`] for more information.
+pub struct CycleSafeIter<I> {
+    iter: Option<I>,
+    visited: HashSet<Entity>,
+}
 
-        assert!(pass.unwrap());
+impl<I: Iterator<Item = Entity>> Iterator for CycleSafeIter<I> {
+    type Item = Result<Entity, HierarchyCycleError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entity = self.iter.as_mut()?.next()?;
+        if self.visited.insert(entity) {
+            Some(Ok(entity))
+        } else {
+            self.iter = None;
+            Some(Err(HierarchyCycleError { entity }))
+        }
+    }
+}
+
+/// An item returned by hierarchy traversal methods which provide additional information about each entity.
+///
+/// See [`HierarchyQuery::traverse_wide`] and [`WorldDescendantsWideIter::items`] for more information.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraversalItem {
+    /// The visited entity.
+    pub entity: Entity,
+    /// The parent of the visited entity.
+    pub parent: Entity,
+    /// The depth of the visited entity relative to the traversal root.
+    ///
+    /// The children of the root have a depth of `1`.
+    pub depth: usize,
+    /// The index of the visited entity among the children of its parent.
+    pub index: usize,
+}
+
+impl TraversalItem {
+    fn children_of(
+        parent: Entity,
+        depth: usize,
+        children: impl Iterator<Item = Entity>,
+    ) -> impl Iterator<Item = Self> {
+        children.enumerate().map(move |(index, entity)| Self {
+            entity,
+            parent,
+            depth: depth + 1,
+            index,
+        })
+    }
+}
+
+/// Controls the flow of a hierarchy traversal using a [`HierarchyVisitor`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VisitControl {
+    /// Continue the traversal into the children of the current entity.
+    #[default]
+    Continue,
+    /// Skip the children of the current entity, but continue the traversal.
+    SkipChildren,
+    /// Stop the traversal immediately.
+    Stop,
+}
+
+/// A trait used to visit entities in a hierarchy in depth-first order.
+///
+/// # Usage
+///
+/// Unlike an iterator, a visitor is notified when each subtree is finished through [`exit`](HierarchyVisitor::exit).
+/// This is useful for serializing trees or generating nested output.
+///
+/// The traversal starts at the root entity, which has a depth of `0`.
+/// For every entity entered, [`exit`](HierarchyVisitor::exit) is called after all its children have been
+/// visited, even if they were skipped. After [`VisitControl::Stop`] is returned, no more callbacks are invoked.
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::hierarchy::{visit_subtree, HierarchyVisitor, VisitControl};
+///
+/// struct Markup<'w>(&'w World, String);
+///
+/// impl HierarchyVisitor for Markup<'_> {
+///     fn enter(&mut self, entity: Entity, _depth: usize) -> VisitControl {
+///         let name = self.0.get::<Name>(entity).unwrap();
+///         self.1 += &format!("<{name}>");
+///         VisitControl::Continue
+///     }
+///
+///     fn exit(&mut self, entity: Entity, _depth: usize) {
+///         let name = self.0.get::<Name>(entity).unwrap();
+///         self.1 += &format!("</{name}>");
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// let a = world.spawn(Name::new("a")).id();
+/// let b = world.spawn((Name::new("b"), ChildOf(a))).id();
+/// world.spawn((Name::new("c"), ChildOf(b)));
+/// world.spawn((Name::new("d"), ChildOf(a)));
+///
+/// let mut markup = Markup(&world, String::new());
+/// visit_subtree::<ChildOf>(&world, a, &mut markup);
+/// assert_eq!(markup.1, "<a><b><c></c></b><d></d></a>");
+/// ```
+pub trait HierarchyVisitor {
+    /// Called when an entity is entered, before any of its children are visited.
+    fn enter(&mut self, entity: Entity, depth: usize) -> VisitControl;
+
+    /// Called when an entity is exited, after all of its children have been visited.
+    fn exit(&mut self, _entity: Entity, _depth: usize) {}
+}
+
+/// Visits the given `entity` and all its descendants in depth-first order using the given [`HierarchyVisitor`].
+///
+/// Returns `false` if the traversal was stopped by the visitor.
+///
+/// See [`HierarchyVisitor`] for more information.
+pub fn visit_subtree<R: Relationship>(
+    world: &World,
+    entity: Entity,
+    visitor: &mut impl HierarchyVisitor,
+) -> bool {
+    visit_subtree_with(entity, |entity| world_children::<R>(world, entity), visitor)
+}
+
+fn visit_subtree_with(
+    root: Entity,
+    children: impl Fn(Entity) -> Vec<Entity>,
+    visitor: &mut impl HierarchyVisitor,
+) -> bool {
+    enum Step {
+        Enter(Entity, usize),
+        Exit(Entity, usize),
+    }
+
+    let mut stack = vec![Step::Enter(root, 0)];
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Enter(entity, depth) => {
+                let control = visitor.enter(entity, depth);
+                if control == VisitControl::Stop {
+                    return false;
+                }
+
+                stack.push(Step::Exit(entity, depth));
+                if control == VisitControl::Continue {
+                    let children = children(entity).into_iter().rev();
+                    stack.extend(children.map(|child| Step::Enter(child, depth + 1)));
+                }
+            }
+            Step::Exit(entity, depth) => visitor.exit(entity, depth),
+        }
+    }
+
+    true
+}
+
+/// A predicate used to filter entities during [`World`] hierarchy traversal.
+///
+/// See [`WorldDescendantsWideIter::visit`] and [`WorldDescendantsWideIter::prune`] for more information.
+pub type WorldTraversalPredicate<'w> = Box<dyn 'w + FnMut(EntityRef<'w>) -> bool + Send + Sync>;
+
+/// Iterator for breadth-first traversal of descendants.
+pub struct WorldDescendantsWideIter<'w, R: Relationship = ChildOf> {
+    world: &'w World,
+    queue: VecDeque<TraversalItem>,
+    visit: Option<WorldTraversalPredicate<'w>>,
+    prune: Option<WorldTraversalPredicate<'w>>,
+    max_depth: Option<usize>,
+    _marker: PhantomData<R>,
+}
+
+impl<'w, R: Relationship> WorldDescendantsWideIter<'w, R> {
+    /// Creates a new [`WorldDescendantsWideIter`] to iterate over all descendants of the given
+    /// [`Entity`] in breadth-first order.
+    ///
+    /// Descendants which no longer exist in the world are still returned, but their children are never visited,
+    /// and they are never passed to [`visit`](Self::visit) or [`prune`](Self::prune).
+    pub fn new(world: &'w World, root: Entity) -> Self {
+        let mut queue = VecDeque::new();
+
+        if let Some(children) = world.get::<R::RelationshipTarget>(root) {
+            queue.extend(TraversalItem::children_of(root, 0, children.iter()));
+        }
+
+        Self {
+            world,
+            queue,
+            visit: None,
+            prune: None,
+            max_depth: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Only returns the descendants for which the given predicate returns `true`.
+    ///
+    /// The children of skipped descendants are still visited. See [`prune`](Self::prune) to skip entire subtrees.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy::prelude::*;
+    /// use moonshine_util::hierarchy::WorldDescendantsWideIter;
+    ///
+    /// #[derive(Component)]
+    /// struct Hidden;
+    ///
+    /// fn visible_descendants(world: &World, entity: Entity) -> Vec<Entity> {
+    ///     WorldDescendantsWideIter::<ChildOf>::new(world, entity)
+    ///         .visit(|entity| !entity.contains::<Hidden>())
+    ///         .prune(|entity| entity.contains::<Hidden>())
+    ///         .collect()
+    /// }
+    /// ```
+    pub fn visit(
+        mut self,
+        predicate: impl 'w + FnMut(EntityRef<'w>) -> bool + Send + Sync,
+    ) -> Self {
+        self.visit = Some(Box::new(predicate));
+        self
+    }
+
+    /// Never visits the children of any descendant for which the given predicate returns `true`.
+    ///
+    /// The pruned descendant itself is still returned, unless it is skipped by [`visit`](Self::visit).
+    pub fn prune(
+        mut self,
+        predicate: impl 'w + FnMut(EntityRef<'w>) -> bool + Send + Sync,
+    ) -> Self {
+        self.prune = Some(Box::new(predicate));
+        self
+    }
+
+    /// Never visits descendants deeper than the given depth.
+    ///
+    /// The children of the root have a depth of `1`.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self.queue.retain(|item| item.depth <= depth);
+        self
+    }
+
+    /// Returns the next [`TraversalItem`] in this traversal.
+    pub fn next_item(&mut self) -> Option<TraversalItem> {
+        loop {
+            let current = self.queue.pop_front()?;
+
+            let Ok(entity) = self.world.get_entity(current.entity) else {
+                // Children which no longer exist are returned, but never expanded.
+                return Some(current);
+            };
+
+            if self
+                .max_depth
+                .is_none_or(|max_depth| current.depth < max_depth)
+                && !self.prune.as_mut().is_some_and(|prune| prune(entity))
+            {
+                if let Some(children) = entity.get::<R::RelationshipTarget>() {
+                    self.queue.extend(TraversalItem::children_of(
+                        current.entity,
+                        current.depth,
+                        children.iter(),
+                    ));
+                }
+            }
+
+            if self.visit.as_mut().is_none_or(|visit| visit(entity)) {
+                return Some(current);
+            }
+        }
+    }
+
+    /// Converts this iterator into an iterator of [`TraversalItem`]s.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use bevy::prelude::*;
+    /// use moonshine_util::hierarchy::WorldDescendantsWideIter;
+    ///
+    /// let mut world = World::new();
+    /// let a = world.spawn_empty().id();
+    /// let b = world.spawn(ChildOf(a)).id();
+    /// let c = world.spawn(ChildOf(b)).id();
+    ///
+    /// let depths: Vec<_> = WorldDescendantsWideIter::<ChildOf>::new(&world, a)
+    ///     .items()
+    ///     .map(|item| (item.entity, item.depth))
+    ///     .collect();
+    ///
+    /// assert_eq!(depths, [(b, 1), (c, 2)]);
+    /// ```
+    pub fn items(mut self) -> impl Iterator<Item = TraversalItem> + 'w
+    where
+        R: 'w,
+    {
+        std::iter::from_fn(move || self.next_item())
+    }
+}
+
+impl<R: Relationship> Iterator for WorldDescendantsWideIter<'_, R> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_item().map(|item| item.entity)
+    }
+}
+
+/// Iterator for depth-first traversal of descendants.
+pub struct WorldDescendantsDeepIter<'w, R: Relationship = ChildOf> {
+    world: &'w World,
+    stack: Vec<TraversalItem>,
+    visit: Option<WorldTraversalPredicate<'w>>,
+    prune: Option<WorldTraversalPredicate<'w>>,
+    max_depth: Option<usize>,
+    _marker: PhantomData<R>,
+}
+
+impl<'w, R: Relationship> WorldDescendantsDeepIter<'w, R> {
+    /// Creates a new [`WorldDescendantsDeepIter`] to iterate over all descendants of the given
+    /// [`Entity`] in depth-first order.
+    ///
+    /// See [`WorldDescendantsWideIter::new`] for more information.
+    pub fn new(world: &'w World, root: Entity) -> Self {
+        let mut stack = Vec::new();
+
+        if let Some(children) = world.get::<R::RelationshipTarget>(root) {
+            let children: Vec<TraversalItem> =
+                TraversalItem::children_of(root, 0, children.iter()).collect();
+            stack.extend(children.into_iter().rev());
+        }
+
+        Self {
+            world,
+            stack,
+            visit: None,
+            prune: None,
+            max_depth: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Only returns the descendants for which the given predicate returns `true`.
+    ///
+    /// See [`WorldDescendantsWideIter::visit`] for more information.
+    pub fn visit(
+        mut self,
+        predicate: impl 'w + FnMut(EntityRef<'w>) -> bool + Send + Sync,
+    ) -> Self {
+        self.visit = Some(Box::new(predicate));
+        self
+    }
+
+    /// Never visits the children of any descendant for which the given predicate returns `true`.
+    ///
+    /// See [`WorldDescendantsWideIter::prune`] for more information.
+    pub fn prune(
+        mut self,
+        predicate: impl 'w + FnMut(EntityRef<'w>) -> bool + Send + Sync,
+    ) -> Self {
+        self.prune = Some(Box::new(predicate));
+        self
+    }
+
+    /// Never visits descendants deeper than the given depth.
+    ///
+    /// See [`WorldDescendantsWideIter::max_depth`] for more information.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self.stack.retain(|item| item.depth <= depth);
+        self
+    }
+
+    /// Returns the next [`TraversalItem`] in this traversal.
+    pub fn next_item(&mut self) -> Option<TraversalItem> {
+        loop {
+            let current = self.stack.pop()?;
+
+            let Ok(entity) = self.world.get_entity(current.entity) else {
+                // Children which no longer exist are returned, but never expanded.
+                return Some(current);
+            };
+
+            if self
+                .max_depth
+                .is_none_or(|max_depth| current.depth < max_depth)
+                && !self.prune.as_mut().is_some_and(|prune| prune(entity))
+            {
+                if let Some(children) = entity.get::<R::RelationshipTarget>() {
+                    let children: Vec<TraversalItem> =
+                        TraversalItem::children_of(current.entity, current.depth, children.iter())
+                            .collect();
+                    self.stack.extend(children.into_iter().rev());
+                }
+            }
+
+            if self.visit.as_mut().is_none_or(|visit| visit(entity)) {
+                return Some(current);
+            }
+        }
+    }
+
+    /// Converts this iterator into an iterator of [`TraversalItem`]s.
+    ///
+    /// See [`WorldDescendantsWideIter::items`] for more information.
+    pub fn items(mut self) -> impl Iterator<Item = TraversalItem> + 'w
+    where
+        R: 'w,
+    {
+        std::iter::from_fn(move || self.next_item())
+    }
+}
+
+impl<R: Relationship> Iterator for WorldDescendantsDeepIter<'_, R> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_item().map(|item| item.entity)
+    }
+}
+
+/// A [`SystemParam`] which extends [`HierarchyQuery`] with [`Name`] path lookup.
+///
+/// # Usage
+///
+/// A path is a sequence of entity names separated by `/`, relative to some entity:
+/// - `Body/Arm.L/Hand` resolves to the `Hand` child of `Arm.L`, which is a child of `Body`.
+/// - `..` resolves to the parent of the current entity, and `.` to the entity itself.
+/// - `*` matches any sequence of characters in a name, so `Arm.*` matches both `Arm.L` and `Arm.R`,
+///   and `*` alone matches any child, including ones without a [`Name`].
+/// - An index suffix, such as `Hand[1]`, selects the second child which matches the name pattern.
+///
+/// All [`HierarchyQuery`] methods are also accessible through this query.
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::hierarchy::HierarchyPathQuery;
+///
+/// fn find_hand(body: In<Entity>, query: HierarchyPathQuery) -> Option<Entity> {
+///     query.find_by_path(*body, "Arm.L/Hand")
+/// }
+///
+/// let mut world = World::new();
+/// let body = world.spawn(Name::new("Body")).id();
+/// let arm = world.spawn((Name::new("Arm.L"), ChildOf(body))).id();
+/// let hand = world.spawn((Name::new("Hand"), ChildOf(arm))).id();
+///
+/// # use bevy::ecs::system::RunSystemOnce;
+/// assert_eq!(world.run_system_once_with(find_hand, body).unwrap(), Some(hand));
+/// ```
+#[derive(SystemParam)]
+pub struct HierarchyPathQuery<'w, 's, R: Relationship = ChildOf> {
+    hierarchy: HierarchyQuery<'w, 's, R>,
+    names: Query<'w, 's, &'static Name>,
+}
+
+impl<'w, 's, R: Relationship> Deref for HierarchyPathQuery<'w, 's, R> {
+    type Target = HierarchyQuery<'w, 's, R>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.hierarchy
+    }
+}
+
+impl<R: Relationship> HierarchyPathQuery<'_, '_, R> {
+    /// Returns the first entity which matches the given `path`, relative to the given `entity`.
+    ///
+    /// See [`HierarchyPathQuery`] for details on path syntax.
+    pub fn find_by_path(&self, entity: Entity, path: &str) -> Option<Entity> {
+        self.find_all_by_path(entity, path).into_iter().next()
+    }
+
+    /// Returns all entities which match the given `path`, relative to the given `entity`.
+    ///
+    /// See [`HierarchyPathQuery`] for details on path syntax.
+    pub fn find_all_by_path(&self, entity: Entity, path: &str) -> Vec<Entity> {
+        resolve_path(
+            entity,
+            path,
+            |entity| self.parent(entity),
+            |entity| self.children(entity).collect(),
+            |entity| self.names.get(entity).ok().map(Name::as_str),
+        )
+    }
+
+    /// Returns the path of the given `entity`, relative to the root of its hierarchy.
+    ///
+    /// The returned path always resolves back to the given entity from the root.
+    /// Entities whose names cannot be used as a path segment, such as names which contain `*` or `/`,
+    /// are referenced by their index instead, as in `*[2]`.
+    ///
+    /// Returns [`None`] if any entity along the path is not listed among the children of its parent.
+    /// See [`HierarchyPathQuery`] for details on path syntax.
+    pub fn entity_path(&self, entity: Entity) -> Option<String> {
+        self.entity_path_from(self.root(entity), entity)
+    }
+
+    /// Returns the path of the given `entity`, relative to the given `ancestor`.
+    ///
+    /// Returns [`None`] if `ancestor` is not an ancestor of `entity` or the entity itself,
+    /// or if any entity along the path is not listed among the children of its parent.
+    pub fn entity_path_from(&self, ancestor: Entity, entity: Entity) -> Option<String> {
+        build_path(
+            ancestor,
+            entity,
+            |entity| self.parent(entity),
+            |entity| self.children(entity).collect(),
+            |entity| self.names.get(entity).ok().map(Name::as_str),
+        )
+    }
+}
+
+/// Returns the first entity which matches the given `path`, relative to the given `entity`.
+///
+/// See [`HierarchyPathQuery`] for details on path syntax.
+pub fn find_by_path<R: Relationship>(world: &World, entity: Entity, path: &str) -> Option<Entity> {
+    find_all_by_path::<R>(world, entity, path)
+        .into_iter()
+        .next()
+}
+
+/// Returns all entities which match the given `path`, relative to the given `entity`.
+///
+/// See [`HierarchyPathQuery`] for details on path syntax.
+pub fn find_all_by_path<R: Relationship>(world: &World, entity: Entity, path: &str) -> Vec<Entity> {
+    resolve_path(
+        entity,
+        path,
+        |entity| world_parent::<R>(world, entity),
+        |entity| world_children::<R>(world, entity),
+        |entity| world.get::<Name>(entity).map(Name::as_str),
+    )
+}
+
+/// Returns the path of the given `entity`, relative to the root of its hierarchy.
+///
+/// See [`HierarchyPathQuery::entity_path`] for more information.
+pub fn entity_path<R: Relationship>(world: &World, entity: Entity) -> Option<String> {
+    let mut root = entity;
+    while let Some(parent) = world_parent::<R>(world, root) {
+        root = parent;
+    }
+    entity_path_from::<R>(world, root, entity)
+}
+
+/// Returns the path of the given `entity`, relative to the given `ancestor`.
+///
+/// See [`HierarchyPathQuery::entity_path_from`] for more information.
+pub fn entity_path_from<R: Relationship>(
+    world: &World,
+    ancestor: Entity,
+    entity: Entity,
+) -> Option<String> {
+    build_path(
+        ancestor,
+        entity,
+        |entity| world_parent::<R>(world, entity),
+        |entity| world_children::<R>(world, entity),
+        |entity| world.get::<Name>(entity).map(Name::as_str),
+    )
+}
+
+/// Iterates over the siblings of the given entity, excluding the entity itself.
+///
+/// See [`HierarchyQuery::siblings`] for more information.
+pub fn siblings<R: Relationship>(world: &World, entity: Entity) -> impl Iterator<Item = Entity> {
+    world_parent::<R>(world, entity)
+        .map(|parent| world_children::<R>(world, parent))
+        .unwrap_or_default()
+        .into_iter()
+        .filter(move |&sibling| sibling != entity)
+}
+
+/// Returns the index of the given entity among the children of its parent, if it has one.
+///
+/// See [`HierarchyQuery::sibling_index`] for more information.
+pub fn sibling_index<R: Relationship>(world: &World, entity: Entity) -> Option<usize> {
+    let parent = world_parent::<R>(world, entity)?;
+    world_children::<R>(world, parent)
+        .into_iter()
+        .position(|child| child == entity)
+}
+
+/// Returns the sibling which comes after the given entity among the children of its parent, if any.
+///
+/// See [`HierarchyQuery::next_sibling`] for more information.
+pub fn next_sibling<R: Relationship>(world: &World, entity: Entity) -> Option<Entity> {
+    let parent = world_parent::<R>(world, entity)?;
+    let children = world_children::<R>(world, parent);
+    let index = children.iter().position(|&child| child == entity)?;
+    children.get(index + 1).copied()
+}
+
+/// Returns the sibling which comes before the given entity among the children of its parent, if any.
+///
+/// See [`HierarchyQuery::prev_sibling`] for more information.
+pub fn prev_sibling<R: Relationship>(world: &World, entity: Entity) -> Option<Entity> {
+    let parent = world_parent::<R>(world, entity)?;
+    let children = world_children::<R>(world, parent);
+    let index = children.iter().position(|&child| child == entity)?;
+    index.checked_sub(1).map(|index| children[index])
+}
+
+fn world_parent<R: Relationship>(world: &World, entity: Entity) -> Option<Entity> {
+    world.get::<R>(entity).map(Relationship::get)
+}
+
+fn world_children<R: Relationship>(world: &World, entity: Entity) -> Vec<Entity> {
+    world
+        .get::<R::RelationshipTarget>(entity)
+        .map(|children| children.iter().collect())
+        .unwrap_or_default()
+}
+
+fn resolve_path<'a>(
+    entity: Entity,
+    path: &str,
+    parent: impl Fn(Entity) -> Option<Entity>,
+    children: impl Fn(Entity) -> Vec<Entity>,
+    name: impl Fn(Entity) -> Option<&'a str>,
+) -> Vec<Entity> {
+    let mut current = vec![entity];
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => {
+                let mut next = Vec::new();
+                for parent in current.into_iter().filter_map(&parent) {
+                    if !next.contains(&parent) {
+                        next.push(parent);
+                    }
+                }
+                current = next;
+            }
+            _ => {
+                let (pattern, index) = parse_segment(segment);
+                let mut next = Vec::new();
+                for entity in current {
+                    let matches = children(entity)
+                        .into_iter()
+                        .filter(|&child| glob_match(pattern, name(child).unwrap_or_default()));
+                    match index {
+                        Some(index) => next.extend(matches.skip(index).take(1)),
+                        None => next.extend(matches),
+                    }
+                }
+                current = next;
+            }
+        }
+
+        if current.is_empty() {
+            break;
+        }
+    }
+    current
+}
+
+fn build_path<'a>(
+    ancestor: Entity,
+    entity: Entity,
+    parent: impl Fn(Entity) -> Option<Entity>,
+    children: impl Fn(Entity) -> Vec<Entity>,
+    name: impl Fn(Entity) -> Option<&'a str>,
+) -> Option<String> {
+    let mut segments = Vec::new();
+    let mut current = entity;
+    while current != ancestor {
+        let parent = parent(current)?;
+        let siblings = children(parent);
+        let position = siblings.iter().position(|&sibling| sibling == current)?;
+        let segment = match name(current).filter(|&name| is_path_name(name)) {
+            Some(current_name) => {
+                let index = siblings[..position]
+                    .iter()
+                    .filter(|&&sibling| glob_match(current_name, name(sibling).unwrap_or_default()))
+                    .count();
+                if index == 0 {
+                    current_name.to_string()
+                } else {
+                    format!("{current_name}[{index}]")
+                }
+            }
+            None => format!("*[{position}]"),
+        };
+        segments.push(segment);
+        current = parent;
+    }
+    segments.reverse();
+    Some(segments.join("/"))
+}
+
+/// Returns `true` if the given name resolves only to itself when used as a path segment.
+fn is_path_name(name: &str) -> bool {
+    !matches!(name, "" | "." | "..")
+        && !name.contains(['*', '/'])
+        && parse_segment(name).1.is_none()
+}
+
+fn parse_segment(segment: &str) -> (&str, Option<usize>) {
+    if let Some(prefix) = segment.strip_suffix(']') {
+        if let Some((pattern, index)) = prefix.rsplit_once('[') {
+            if let Ok(index) = index.parse() {
+                return (pattern, Some(index));
+            }
+        }
+    }
+    (segment, None)
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let Some((head, tail)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+
+    let Some(mut rest) = text.strip_prefix(head) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = tail.split('*').collect();
+    let last = parts.pop().unwrap();
+    for part in parts {
+        let Some(index) = rest.find(part) else {
+            return false;
+        };
+        rest = &rest[index + part.len()..];
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// A structural issue found in a [`Relationship`] hierarchy by [`validate_hierarchy`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HierarchyIssue {
+    /// The given entity is related to itself.
+    SelfReference {
+        /// The self-referencing entity.
+        entity: Entity,
+    },
+    /// The given `source` entity is related to a `target` entity which does not exist.
+    DanglingTarget {
+        /// The entity which contains the [`Relationship`].
+        source: Entity,
+        /// The target entity which does not exist.
+        target: Entity,
+    },
+    /// The given `source` entity is related to `target`, but it is not listed as a source of `target`.
+    UnlistedSource {
+        /// The entity which contains the [`Relationship`].
+        source: Entity,
+        /// The target entity which does not list the source entity.
+        target: Entity,
+    },
+    /// The given `target` entity lists `source` as a source, but `source` is not related to `target`.
+    MismatchedSource {
+        /// The entity which contains the [`RelationshipTarget`].
+        target: Entity,
+        /// The listed source entity which is not related to the target.
+        source: Entity,
+    },
+    /// The given `target` entity lists `source` as a source more than once.
+    DuplicateSource {
+        /// The entity which contains the [`RelationshipTarget`].
+        target: Entity,
+        /// The duplicated source entity.
+        source: Entity,
+    },
+    /// The given entities form a cycle, where each entity is related to the next,
+    /// and the last entity is related to the first.
+    Cycle {
+        /// The entities in the cycle, starting with the lowest entity.
+        entities: Vec<Entity>,
+    },
+}
+
+impl fmt::Display for HierarchyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SelfReference { entity } => write!(f, "entity {entity} is related to itself"),
+            Self::DanglingTarget { source, target } => {
+                write!(
+                    f,
+                    "entity {source} is related to {target}, which does not exist"
+                )
+            }
+            Self::UnlistedSource { source, target } => {
+                write!(
+                    f,
+                    "entity {source} is related to {target}, but is not listed as its source"
+                )
+            }
+            Self::MismatchedSource { target, source } => {
+                write!(f, "entity {target} lists {source} as its source, but {source} is not related to it")
+            }
+            Self::DuplicateSource { target, source } => {
+                write!(
+                    f,
+                    "entity {target} lists {source} as its source more than once"
+                )
+            }
+            Self::Cycle { entities } => {
+                write!(f, "entities form a cycle: ")?;
+                for entity in entities {
+                    write!(f, "{entity} -> ")?;
+                }
+                write!(f, "{}", entities[0])
+            }
+        }
+    }
+}
+
+/// A report of all structural issues in a [`Relationship`] hierarchy.
+///
+/// See [`validate_hierarchy`] for more information.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HierarchyReport {
+    /// All issues found in the hierarchy.
+    pub issues: Vec<HierarchyIssue>,
+}
+
+impl HierarchyReport {
+    /// Returns `true` if no issues were found in the hierarchy.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Validates the structure of all entities related by the given [`Relationship`].
+///
+/// # Usage
+///
+/// Bevy maintains the consistency of relationships automatically. However, relationships which
+/// are modified using risky methods or incorrectly cloned may become invalid. Relationships which
+/// are not [`ChildOf`] are also not guaranteed to be acyclic, and hierarchy traversal of a cyclic
+/// relationship never terminates.
+///
+/// This function detects cycles, self-references, dangling targets, and any mismatches between
+/// the [`Relationship`] and its [`RelationshipTarget`].
+///
+/// See [`HierarchyValidationPlugin`] to validate a hierarchy every frame.
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::hierarchy::validate_hierarchy;
+///
+/// let mut world = World::new();
+/// let a = world.spawn_empty().id();
+/// let b = world.spawn(ChildOf(a)).id();
+/// assert!(validate_hierarchy::<ChildOf>(&world).is_valid());
+/// ```
+pub fn validate_hierarchy<R: Relationship>(world: &World) -> HierarchyReport {
+    let mut issues = Vec::new();
+
+    let mut parents = HashMap::new();
+    if let Some(mut query) = world.try_query::<(Entity, &R)>() {
+        for (source, relationship) in query.iter(world) {
+            let target = relationship.get();
+            parents.insert(source, target);
+
+            if source == target {
+                issues.push(HierarchyIssue::SelfReference { entity: source });
+            } else if !world.entities().contains(target) {
+                issues.push(HierarchyIssue::DanglingTarget { source, target });
+            } else if !world
+                .get::<R::RelationshipTarget>(target)
+                .is_some_and(|sources| sources.iter().any(|entity| entity == source))
+            {
+                issues.push(HierarchyIssue::UnlistedSource { source, target });
+            }
+        }
+    }
+
+    if let Some(mut query) = world.try_query::<(Entity, &R::RelationshipTarget)>() {
+        for (target, sources) in query.iter(world) {
+            let mut visited = HashSet::new();
+            for source in sources.iter() {
+                if !visited.insert(source) {
+                    issues.push(HierarchyIssue::DuplicateSource { target, source });
+                } else if parents.get(&source) != Some(&target) {
+                    issues.push(HierarchyIssue::MismatchedSource { target, source });
+                }
+            }
+        }
+    }
+
+    let mut done = HashSet::new();
+    for &start in parents.keys() {
+        let mut path = Vec::new();
+        let mut current = start;
+        while !done.contains(&current) {
+            if let Some(index) = path.iter().position(|&entity| entity == current) {
+                let mut entities: Vec<Entity> = path[index..].to_vec();
+                if entities.len() > 1 {
+                    let min = (0..entities.len()).min_by_key(|&i| entities[i]).unwrap();
+                    entities.rotate_left(min);
+                    issues.push(HierarchyIssue::Cycle { entities });
+                }
+                break;
+            }
+
+            path.push(current);
+            let Some(&parent) = parents.get(&current) else {
+                break;
+            };
+            current = parent;
+        }
+        done.extend(path);
+    }
+
+    HierarchyReport { issues }
+}
+
+/// A [`Plugin`] which validates the structure of a [`Relationship`] hierarchy every frame.
+///
+/// Any issues found by [`validate_hierarchy`] are logged as errors.
+/// This is intended for debugging, as validating large hierarchies every frame may be expensive.
+pub struct HierarchyValidationPlugin<R: Relationship = ChildOf>(PhantomData<R>);
+
+impl<R: Relationship> Default for HierarchyValidationPlugin<R> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<R: Relationship> Plugin for HierarchyValidationPlugin<R> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Last, log_hierarchy_issues::<R>);
+    }
+}
+
+/// A [`System`] which logs all issues found by [`validate_hierarchy`] as errors.
+pub fn log_hierarchy_issues<R: Relationship>(world: &World) {
+    for issue in validate_hierarchy::<R>(world).issues {
+        error!("invalid {} hierarchy: {issue}", get_short_type_name::<R>());
+    }
+}
+
+/// A [`Component`] whose value is inherited by descendants in a [`Relationship`] hierarchy.
+///
+/// # Usage
+///
+/// Each entity with this component has a local value. With [`InheritPlugin`], the local value
+/// is combined with the inherited value of the nearest ancestor with the same component, and the
+/// result is stored in an [`Inherited<T>`] component on the entity.
+///
+/// Entities without the component do not receive an [`Inherited<T>`], but the inherited value
+/// is still passed down to their descendants.
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::hierarchy::{Inherit, InheritPlugin, Inherited};
+///
+/// #[derive(Component, Clone, PartialEq, Debug)]
+/// struct Visible(bool);
+///
+/// impl Inherit for Visible {
+///     fn inherit(&self, parent: &Self) -> Self {
+///         Visible(self.0 && parent.0)
+///     }
+/// }
+///
+/// let mut app = App::new();
+/// app.add_plugins(InheritPlugin::<Visible>::default());
+///
+/// let parent = app.world_mut().spawn(Visible(false)).id();
+/// let child = app.world_mut().spawn((Visible(true), ChildOf(parent))).id();
+/// app.update();
+///
+/// let inherited = app.world().get::<Inherited<Visible>>(child).unwrap();
+/// assert_eq!(**inherited, Visible(false));
+/// ```
+pub trait Inherit: Component + Clone {
+    /// Combines this local value with the inherited value of the parent.
+    fn inherit(&self, parent: &Self) -> Self;
+}
+
+/// A [`Component`] which stores the inherited value of an [`Inherit`] component.
+///
+/// See [`Inherit`] for more information.
+#[derive(Component, Clone, Debug)]
+pub struct Inherited<T: Inherit>(T);
+
+impl<T: Inherit> Inherited<T> {
+    /// Returns the inherited value.
+    pub fn get(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Inherit> Deref for Inherited<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// A [`Plugin`] which maintains [`Inherited<T>`] for all entities with `T` in a [`Relationship`] hierarchy.
+///
+/// See [`Inherit`] for more information.
+pub struct InheritPlugin<T: Inherit, R: Relationship = ChildOf>(PhantomData<(T, R)>);
+
+impl<T: Inherit, R: Relationship> Default for InheritPlugin<T, R> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: Inherit, R: Relationship> Plugin for InheritPlugin<T, R> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, propagate_inherited::<T, R>);
+    }
+}
+
+/// A [`System`] which updates [`Inherited<T>`] for all entities with `T` in a [`Relationship`] hierarchy.
+///
+/// Only the subtrees of entities whose `T` or `R` has changed, or was removed, are updated.
+///
+/// See [`InheritPlugin`] for more information.
+#[allow(clippy::type_complexity)]
+pub fn propagate_inherited<T: Inherit, R: Relationship>(
+    changed: Query<Entity, Or<(Changed<T>, Changed<R>)>>,
+    mut removed_parents: RemovedComponents<R>,
+    mut removed_values: RemovedComponents<T>,
+    mut hierarchy: HierarchyQuery<R, (Option<&'static T>, Option<&'static mut Inherited<T>>)>,
+    mut commands: Commands,
+) {
+    let mut dirty: HashSet<Entity> = changed.iter().collect();
+    dirty.extend(removed_parents.read());
+
+    for entity in removed_values.read() {
+        if let Some((None, Some(_))) = hierarchy.get(entity) {
+            commands.entity(entity).remove::<Inherited<T>>();
+        }
+        dirty.extend(hierarchy.children(entity));
+    }
+
+    dirty.retain(|&entity| hierarchy.get(entity).is_some());
+
+    let roots: Vec<Entity> = dirty
+        .iter()
+        .copied()
+        .filter(|&entity| !hierarchy.ancestors(entity).any(|a| dirty.contains(&a)))
+        .collect();
+
+    for root in roots {
+        let inherited = hierarchy
+            .ancestors_with_data(root)
+            .find_map(|(_, data)| match data {
+                (Some(_), Some(inherited)) => Some(inherited.get().clone()),
+                _ => None,
+            });
+
+        let mut stack = vec![(root, inherited)];
+        while let Some((entity, inherited)) = stack.pop() {
+            let inherited = match hierarchy.get_mut(entity) {
+                Some((Some(local), current)) => {
+                    let value = match &inherited {
+                        Some(parent) => local.inherit(parent),
+                        None => local.clone(),
+                    };
+                    match current {
+                        Some(mut current) => current.0 = value.clone(),
+                        None => {
+                            commands.entity(entity).insert(Inherited(value.clone()));
+                        }
+                    }
+                    Some(value)
+                }
+                _ => inherited,
+            };
+
+            stack.extend(
+                hierarchy
+                    .children(entity)
+                    .map(|child| (child, inherited.clone())),
+            );
+        }
+    }
+}
+
+/// A [`Message`] sent when the ancestors of an entity in a [`Relationship`] hierarchy have changed.
+///
+/// # Usage
+///
+/// When an entity is reparented, only its own relationship component changes. However, the ancestors of all its
+/// descendants change as well. With [`AncestryChangedPlugin`], this message is sent for the reparented entity and
+/// every one of its descendants.
+///
+/// This message is also sent when an entity is added to a hierarchy, or when its relationship is removed, such as
+/// when its parent is despawned.
+///
+/// All changes are batched, and each entity is reported at most once per frame.
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::hierarchy::{AncestryChanged, AncestryChangedPlugin};
+///
+/// let mut app = App::new();
+/// app.add_plugins(AncestryChangedPlugin::<ChildOf>::default());
+///
+/// let a = app.world_mut().spawn_empty().id();
+/// let b = app.world_mut().spawn_empty().id();
+/// let c = app.world_mut().spawn(ChildOf(b)).id();
+/// app.update();
+///
+/// app.world_mut().entity_mut(b).insert(ChildOf(a));
+/// app.update();
+///
+/// let messages = app.world().resource::<Messages<AncestryChanged>>();
+/// let changed: Vec<Entity> = messages.iter_current_update_messages().map(|m| m.entity).collect();
+/// assert_eq!(changed, [b, c]);
+/// ```
+#[derive(Message, Debug)]
+pub struct AncestryChanged<R: Relationship = ChildOf> {
+    /// The entity whose ancestors have changed.
+    pub entity: Entity,
+    marker: PhantomData<R>,
+}
+
+impl<R: Relationship> AncestryChanged<R> {
+    /// Creates a new [`AncestryChanged`] message for the given `entity`.
+    pub fn new(entity: Entity) -> Self {
+        Self {
+            entity,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// A [`Plugin`] which sends [`AncestryChanged`] messages for a [`Relationship`] hierarchy.
+///
+/// See [`AncestryChanged`] for more information.
+pub struct AncestryChangedPlugin<R: Relationship = ChildOf>(PhantomData<R>);
+
+impl<R: Relationship> Default for AncestryChangedPlugin<R> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<R: Relationship> Plugin for AncestryChangedPlugin<R> {
+    fn build(&self, app: &mut App) {
+        app.add_message::<AncestryChanged<R>>()
+            .add_systems(Last, send_ancestry_changed::<R>);
+    }
+}
+
+/// A [`System`] which sends [`AncestryChanged`] messages for all entities whose ancestors have changed.
+///
+/// Changes to a [`Relationship`] which do not change its target, such as edits to any additional data
+/// in the relationship component, are ignored. This includes relationships which are removed and inserted again
+/// with the same target since the last time this system ran.
+///
+/// See [`AncestryChangedPlugin`] for more information.
+pub fn send_ancestry_changed<R: Relationship>(
+    changed: Query<(Entity, &R), Changed<R>>,
+    mut removed: RemovedComponents<R>,
+    relationships: Query<Has<R>>,
+    hierarchy: HierarchyQuery<R>,
+    mut targets: Local<EntityHashMap<Entity>>,
+    mut messages: MessageWriter<AncestryChanged<R>>,
+) {
+    let mut roots = Vec::new();
+    for entity in removed.read() {
+        match relationships.get(entity) {
+            // The relationship was inserted again, so it is compared against its previous target below.
+            Ok(true) => {}
+            Ok(false) => {
+                if targets.remove(&entity).is_some() {
+                    roots.push(entity);
+                }
+            }
+            // The entity was despawned.
+            Err(_) => {
+                targets.remove(&entity);
+            }
+        }
+    }
+
+    for (entity, relationship) in &changed {
+        if targets.insert(entity, relationship.get()) != Some(relationship.get()) {
+            roots.push(entity);
+        }
+    }
+
+    let mut visited = HashSet::new();
+    let mut batch = Vec::new();
+    for entity in roots {
+        if visited.contains(&entity) {
+            continue;
+        }
+
+        for entity in std::iter::once(entity).chain(hierarchy.descendants_wide(entity)) {
+            if visited.insert(entity) {
+                batch.push(AncestryChanged::new(entity));
+            }
+        }
+    }
+
+    messages.write_batch(batch);
+}
+
+/// The output style of a [`HierarchyDump`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HierarchyDumpStyle {
+    /// An indented ASCII tree.
+    ///
+    /// ```text
+    /// 0v0 "Root"
+    /// |-- 1v0 "A"
+    /// |   `-- 3v0
+    /// `-- 2v0 "B"
+    /// ```
+    #[default]
+    Ascii,
+    /// A nested Markdown list.
+    ///
+    /// ```text
+    /// - 0v0 "Root"
+    ///   - 1v0 "A"
+    ///     - 3v0
+    ///   - 2v0 "B"
+    /// ```
+    Markdown,
+}
+
+/// A text representation of a [`Relationship`] hierarchy, used for debugging and snapshot tests.
+///
+/// # Usage
+///
+/// Each line shows the entity, its [`Name`] (if any), and optionally a list of its components.
+/// Children are listed in the order of their relationship target, and components are sorted by their short name,
+/// so the output is deterministic for any given world.
+///
+/// Each entity is expanded at most once. If an entity is reached again, it is written as
+/// `(cycle: <entity>)` if it is one of its own ancestors, or `(repeated: <entity>)` otherwise.
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::hierarchy::HierarchyDump;
+///
+/// #[derive(Component)]
+/// struct Body;
+///
+/// let mut world = World::new();
+/// let root = world.spawn(Name::new("Root")).id();
+/// let body = world.spawn((Name::new("Body"), Body, ChildOf(root))).id();
+/// let other = world.spawn(ChildOf(root)).id();
+///
+/// let dump = HierarchyDump::<ChildOf>::new(&world, root).component::<Body>().to_string();
+/// assert_eq!(dump, format!("{root} \"Root\"\n|-- {body} \"Body\" [Body]\n`-- {other}\n"));
+/// ```
+pub struct HierarchyDump<'w, R: Relationship = ChildOf> {
+    world: &'w World,
+    root: Entity,
+    style: HierarchyDumpStyle,
+    components: HierarchyDumpComponents,
+    marker: PhantomData<R>,
+}
+
+enum HierarchyDumpComponents {
+    None,
+    All,
+    Only(Vec<(ComponentId, String)>),
+}
+
+impl<'w, R: Relationship> HierarchyDump<'w, R> {
+    /// Creates a new [`HierarchyDump`] of the given `root` entity and all its descendants.
+    pub fn new(world: &'w World, root: Entity) -> Self {
+        Self {
+            world,
+            root,
+            style: HierarchyDumpStyle::default(),
+            components: HierarchyDumpComponents::None,
+            marker: PhantomData,
+        }
+    }
+
+    /// Sets the output style of this dump.
+    pub fn style(mut self, style: HierarchyDumpStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Lists all components of each entity.
+    ///
+    /// Note that component names are only available if the `debug` feature of `bevy` is enabled.
+    pub fn all_components(mut self) -> Self {
+        self.components = HierarchyDumpComponents::All;
+        self
+    }
+
+    /// Lists the given component for each entity which has it.
+    ///
+    /// This may be called multiple times to list multiple components.
+    pub fn component<T: Component>(mut self) -> Self {
+        let id = self
+            .world
+            .components()
+            .get_id(TypeId::of::<T>())
+            .map(|id| (id, get_short_type_name::<T>()));
+        match &mut self.components {
+            HierarchyDumpComponents::All => {}
+            HierarchyDumpComponents::Only(ids) => ids.extend(id),
+            components => *components = HierarchyDumpComponents::Only(id.into_iter().collect()),
+        }
+        self
+    }
+
+    fn label(&self, entity: Entity) -> String {
+        let mut label = entity.to_string();
+
+        if let Some(name) = self.world.get::<Name>(entity) {
+            label += &format!(" {:?}", name.as_str());
+        }
+
+        let mut names: Vec<String> = match &self.components {
+            HierarchyDumpComponents::None => Vec::new(),
+            HierarchyDumpComponents::All => self
+                .world
+                .inspect_entity(entity)
+                .into_iter()
+                .flatten()
+                .map(|info| get_short_name(&info.name().to_string()))
+                .collect(),
+            HierarchyDumpComponents::Only(ids) => {
+                let Ok(entity) = self.world.get_entity(entity) else {
+                    return label;
+                };
+                ids.iter()
+                    .filter(|(id, _)| entity.contains_id(*id))
+                    .map(|(_, name)| name.clone())
+                    .collect()
+            }
+        };
+
+        if !names.is_empty() {
+            names.sort();
+            label += &format!(" [{}]", names.join(", "));
+        }
+
+        label
+    }
+
+    fn push_children(
+        &self,
+        stack: &mut Vec<DumpLine>,
+        entity: Entity,
+        prefix: String,
+        depth: usize,
+    ) {
+        let children = world_children::<R>(self.world, entity);
+        let count = children.len();
+        stack.extend(
+            children
+                .into_iter()
+                .enumerate()
+                .rev()
+                .map(|(index, entity)| DumpLine {
+                    entity,
+                    prefix: prefix.clone(),
+                    depth,
+                    last: index + 1 == count,
+                }),
+        );
+    }
+}
+
+/// A pending line of a [`HierarchyDump`].
+struct DumpLine {
+    entity: Entity,
+    prefix: String,
+    depth: usize,
+    last: bool,
+}
+
+impl<R: Relationship> fmt::Display for HierarchyDump<'_, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = self.label(self.root);
+        match self.style {
+            HierarchyDumpStyle::Ascii => writeln!(f, "{label}")?,
+            HierarchyDumpStyle::Markdown => writeln!(f, "- {label}")?,
+        }
+
+        // Lines are written from an explicit stack, so the hierarchy may be arbitrarily deep.
+        let mut visited: HashSet<Entity> = HashSet::from_iter([self.root]);
+        let mut path = vec![self.root];
+        let mut stack = Vec::new();
+        self.push_children(&mut stack, self.root, String::new(), 1);
+        while let Some(DumpLine {
+            entity,
+            prefix,
+            depth,
+            last,
+        }) = stack.pop()
+        {
+            path.truncate(depth);
+            let expand = visited.insert(entity);
+            let label = if expand {
+                self.label(entity)
+            } else if path.contains(&entity) {
+                format!("(cycle: {entity})")
+            } else {
+                format!("(repeated: {entity})")
+            };
+
+            match self.style {
+                HierarchyDumpStyle::Ascii => {
+                    let branch = if last { "`-- " } else { "|-- " };
+                    writeln!(f, "{prefix}{branch}{label}")?;
+                }
+                HierarchyDumpStyle::Markdown => {
+                    writeln!(f, "{}- {label}", "  ".repeat(depth))?;
+                }
+            }
+
+            if expand {
+                path.push(entity);
+                let prefix = match self.style {
+                    HierarchyDumpStyle::Ascii => {
+                        format!("{prefix}{}", if last { "    " } else { "|   " })
+                    }
+                    HierarchyDumpStyle::Markdown => prefix,
+                };
+                self.push_children(&mut stack, entity, prefix, depth + 1);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns a text representation of the given `entity` and all its descendants.
+///
+/// See [`HierarchyDump`] for more information and configuration.
+pub fn dump_hierarchy<R: Relationship>(world: &World, entity: Entity) -> String {
+    HierarchyDump::<R>::new(world, entity).to_string()
+}
+
+/// An [`EntityCommand`] which clones the given entity and all its descendants
+/// in a [`Relationship`] hierarchy.
+///
+/// # Usage
+///
+/// Entities are cloned using [`EntityCloner`], and any [`Entity`] references within the cloned components which point
+/// into the cloned subtree are remapped to their clones. References to entities outside the subtree are kept as is.
+/// This means the clone of the given entity has the same parent as the original.
+///
+/// If a relationship component cannot be cloned (because it implements neither `Clone` nor `Reflect`),
+/// it is created using [`Relationship::from`] instead.
+///
+/// Returns a map of each original entity to its clone, or a [`HierarchyCycleError`] if the hierarchy contains a cycle.
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::hierarchy::clone_subtree;
+///
+/// let mut world = World::new();
+/// let a = world.spawn(Name::new("A")).id();
+/// let b = world.spawn((Name::new("B"), ChildOf(a))).id();
+///
+/// let map = clone_subtree::<ChildOf>(world.entity_mut(a)).unwrap();
+/// let (a2, b2) = (map[&a], map[&b]);
+/// assert_eq!(world.get::<ChildOf>(b2).unwrap().parent(), a2);
+///
+/// // Or using commands:
+/// world.commands().entity(a).queue(clone_subtree::<ChildOf>);
+/// world.flush();
+/// assert_eq!(world.query::<&Name>().iter(&world).count(), 6);
+/// ```
+pub fn clone_subtree<R: Relationship>(
+    entity: EntityWorldMut,
+) -> Result<EntityHashMap<Entity>, HierarchyCycleError> {
+    let root = entity.id();
+    let world = entity.into_world_mut();
+
+    let sources = std::iter::once(root)
+        .chain(WorldDescendantsWideIter::<R>::new(world, root))
+        .cycle_safe()
+        .collect::<Result<Vec<Entity>, _>>()?;
+
+    let mut map = EntityHashMap::default();
+    for &source in &sources {
+        map.insert(source, world.spawn_empty().id());
+    }
+
+    let mut cloner = EntityCloner::build_opt_out(world);
+    cloner.linked_cloning(false);
+    let mut cloner = cloner.finish();
+    for &source in &sources {
+        cloner.clone_entity_mapped(world, source, &mut map);
+    }
+
+    for &source in &sources[1..] {
+        let target = map[&source];
+        if world.get::<R>(target).is_some() {
+            continue;
+        }
+
+        if let Some(parent) = world_parent::<R>(world, source) {
+            let parent = map.get(&parent).copied().unwrap_or(parent);
+            world.entity_mut(target).insert(R::from(parent));
+        }
+    }
+
+    Ok(map)
+}
+
+/// An [`EntityCommand`] which despawns all descendants of the given entity in a [`Relationship`] hierarchy
+/// which match the given [`QueryFilter`], along with all their descendants.
+///
+/// All matching descendants are found before any entity is despawned, and entities are despawned from the
+/// bottom of the hierarchy up. This makes the command safe to use with relationships which despawn their
+/// related entities (`linked_spawn`), and any hooks triggered during despawn cannot affect which entities
+/// are despawned.
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::hierarchy::despawn_descendants_where;
+///
+/// #[derive(Component)]
+/// struct Temporary;
+///
+/// let mut world = World::new();
+/// let a = world.spawn_empty().id();
+/// let b = world.spawn((Temporary, ChildOf(a))).id();
+/// let c = world.spawn(ChildOf(b)).id();
+/// let d = world.spawn(ChildOf(a)).id();
+///
+/// world.commands().entity(a).queue(despawn_descendants_where::<ChildOf, With<Temporary>>);
+/// world.flush();
+///
+/// assert!(world.get_entity(b).is_err());
+/// assert!(world.get_entity(c).is_err());
+/// assert!(world.get_entity(d).is_ok());
+/// ```
+pub fn despawn_descendants_where<R: Relationship, F: QueryFilter + 'static>(
+    entity: EntityWorldMut,
+) {
+    let root = entity.id();
+    let world = entity.into_world_mut();
+    let filter = world.query_filtered::<(), F>();
+
+    let targets = {
+        let world: &World = world;
+        let matches: Vec<Entity> = WorldDescendantsWideIter::<R>::new(world, root)
+            .prune(|entity| filter.get_manual(world, entity.id()).is_ok())
+            .visit(|entity| filter.get_manual(world, entity.id()).is_ok())
+            .collect();
+
+        let mut targets = Vec::new();
+        for entity in matches {
+            targets.push(entity);
+            targets.extend(WorldDescendantsWideIter::<R>::new(world, entity));
+        }
+        targets
+    };
+
+    for entity in targets.into_iter().rev() {
+        if let Ok(entity) = world.get_entity_mut(entity) {
+            entity.despawn();
+        }
+    }
+}
+
+/// An [`EntityCommand`] which detaches the given entity, along with all its descendants,
+/// from its parent in a [`Relationship`] hierarchy.
+///
+/// The given entity becomes the root of its own hierarchy.
+pub fn detach_subtree<R: Relationship>(mut entity: EntityWorldMut) {
+    entity.remove::<R>();
+}
+
+/// Returns an [`EntityCommand`] which moves the given entity, along with all its descendants,
+/// to the given `new_parent` at the given `index` in a [`Relationship`] hierarchy.
+///
+/// If the index is out of bounds, the entity is added as the last child of the new parent.
+///
+/// If the new parent does not exist, or if it is the given entity itself or one of its descendants,
+/// a warning is logged and the hierarchy is not modified.
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::hierarchy::reparent_subtree;
+///
+/// let mut world = World::new();
+/// let a = world.spawn_empty().id();
+/// let b = world.spawn_empty().id();
+/// let c = world.spawn(ChildOf(b)).id();
+/// let d = world.spawn(ChildOf(a)).id();
+///
+/// world.commands().entity(b).queue(reparent_subtree::<ChildOf>(a, 0));
+/// world.flush();
+///
+/// assert_eq!(**world.get::<Children>(a).unwrap(), [b, d]);
+/// assert_eq!(**world.get::<Children>(b).unwrap(), [c]);
+/// ```
+pub fn reparent_subtree<R: Relationship>(new_parent: Entity, index: usize) -> impl EntityCommand
+where
+    <R::RelationshipTarget as RelationshipTarget>::Collection: OrderedRelationshipSourceCollection,
+{
+    move |entity: EntityWorldMut| {
+        let id = entity.id();
+        let world = entity.into_world_mut();
+
+        if world.get_entity(new_parent).is_err() {
+            warn!(
+                "cannot move {id:?} to {new_parent:?} in {} hierarchy; the new parent does not exist",
+                get_short_type_name::<R>()
+            );
+            return;
+        }
+
+        let cyclic = std::iter::once(new_parent)
+            .chain(WorldAncestorsIter::<R>::new(world, new_parent))
+            .cycle_safe()
+            .any(|ancestor| ancestor.is_err() || ancestor == Ok(id));
+        if cyclic {
+            warn!(
+                "cannot move {id:?} to {new_parent:?} in {} hierarchy; this would create a cycle",
+                get_short_type_name::<R>()
+            );
+            return;
+        }
+
+        world
+            .entity_mut(new_parent)
+            .insert_related::<R>(index, &[id]);
+    }
+}
+
+/// Trait used to sort and reorder the children of an entity in a [`Relationship`] hierarchy.
+///
+/// This requires the relationship target collection to be ordered, such as [`Children`].
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::hierarchy::OrderChildren;
+///
+/// #[derive(Component)]
+/// struct Order(i32);
+///
+/// let mut world = World::new();
+/// let parent = world.spawn_empty().id();
+/// let a = world.spawn((Order(2), ChildOf(parent))).id();
+/// let b = world.spawn((Order(1), ChildOf(parent))).id();
+/// let c = world.spawn(ChildOf(parent)).id();
+///
+/// world.entity_mut(parent).sort_children_by_key::<ChildOf, &Order, _>(|order| order.0);
+/// assert_eq!(**world.get::<Children>(parent).unwrap(), [b, a, c]);
+///
+/// world.entity_mut(parent).move_child_to::<ChildOf>(c, 0);
+/// assert_eq!(**world.get::<Children>(parent).unwrap(), [c, b, a]);
+/// ```
+pub trait OrderChildren {
+    /// Sorts the children of this entity by a key extracted from the given [`QueryData`].
+    ///
+    /// The sort is stable, and children which do not match the query data are placed last.
+    fn sort_children_by_key<R, D, K>(
+        &mut self,
+        key: impl Static + FnMut(ROQueryItem<'_, '_, D>) -> K,
+    ) -> &mut Self
+    where
+        R: Relationship,
+        <R::RelationshipTarget as RelationshipTarget>::Collection:
+            OrderedRelationshipSourceCollection,
+        D: ReadOnlyQueryData + 'static,
+        K: Ord;
+
+    /// Adds the given `child` to this entity at the given `index`.
+    ///
+    /// If the child already belongs to this entity, it is moved to the given `index`.
+    /// If the index is out of bounds, the child is added last.
+    fn insert_child_at<R>(&mut self, index: usize, child: Entity) -> &mut Self
+    where
+        R: Relationship,
+        <R::RelationshipTarget as RelationshipTarget>::Collection:
+            OrderedRelationshipSourceCollection;
+
+    /// Moves the given `child` of this entity to the given `index`.
+    ///
+    /// This does nothing if the given entity is not a child of this entity.
+    /// If the index is out of bounds, the child is moved last.
+    fn move_child_to<R>(&mut self, child: Entity, index: usize) -> &mut Self
+    where
+        R: Relationship,
+        <R::RelationshipTarget as RelationshipTarget>::Collection:
+            OrderedRelationshipSourceCollection;
+}
+
+impl OrderChildren for EntityWorldMut<'_> {
+    fn sort_children_by_key<R, D, K>(
+        &mut self,
+        mut key: impl Static + FnMut(ROQueryItem<'_, '_, D>) -> K,
+    ) -> &mut Self
+    where
+        R: Relationship,
+        <R::RelationshipTarget as RelationshipTarget>::Collection:
+            OrderedRelationshipSourceCollection,
+        D: ReadOnlyQueryData + 'static,
+        K: Ord,
+    {
+        let parent = self.id();
+        self.world_scope(|world| {
+            let mut query = world.query::<D>();
+            let mut children: Vec<(Option<K>, Entity)> = world_children::<R>(world, parent)
+                .into_iter()
+                .map(|child| (query.get(world, child).ok().map(&mut key), child))
+                .collect();
+            children.sort_by(|(a, _), (b, _)| compare_keys(a, b));
+
+            if let Some(mut target) = world.get_mut::<R::RelationshipTarget>(parent) {
+                reorder_children(&mut *target, children.into_iter().map(|(_, child)| child));
+            }
+        });
+        self
+    }
+
+    fn insert_child_at<R>(&mut self, index: usize, child: Entity) -> &mut Self
+    where
+        R: Relationship,
+        <R::RelationshipTarget as RelationshipTarget>::Collection:
+            OrderedRelationshipSourceCollection,
+    {
+        self.insert_related::<R>(index, &[child])
+    }
+
+    fn move_child_to<R>(&mut self, child: Entity, index: usize) -> &mut Self
+    where
+        R: Relationship,
+        <R::RelationshipTarget as RelationshipTarget>::Collection:
+            OrderedRelationshipSourceCollection,
+    {
+        if let Some(mut target) = self.get_mut::<R::RelationshipTarget>() {
+            let index = index.min(target.len().saturating_sub(1));
+            target.collection_mut_risky().place(child, index);
+        }
+        self
+    }
+}
+
+impl OrderChildren for EntityCommands<'_> {
+    fn sort_children_by_key<R, D, K>(
+        &mut self,
+        key: impl Static + FnMut(ROQueryItem<'_, '_, D>) -> K,
+    ) -> &mut Self
+    where
+        R: Relationship,
+        <R::RelationshipTarget as RelationshipTarget>::Collection:
+            OrderedRelationshipSourceCollection,
+        D: ReadOnlyQueryData + 'static,
+        K: Ord,
+    {
+        self.queue(move |mut entity: EntityWorldMut| {
+            entity.sort_children_by_key::<R, D, K>(key);
+        })
+    }
+
+    fn insert_child_at<R>(&mut self, index: usize, child: Entity) -> &mut Self
+    where
+        R: Relationship,
+        <R::RelationshipTarget as RelationshipTarget>::Collection:
+            OrderedRelationshipSourceCollection,
+    {
+        self.queue(move |mut entity: EntityWorldMut| {
+            entity.insert_child_at::<R>(index, child);
+        })
+    }
+
+    fn move_child_to<R>(&mut self, child: Entity, index: usize) -> &mut Self
+    where
+        R: Relationship,
+        <R::RelationshipTarget as RelationshipTarget>::Collection:
+            OrderedRelationshipSourceCollection,
+    {
+        self.queue(move |mut entity: EntityWorldMut| {
+            entity.move_child_to::<R>(child, index);
+        })
+    }
+}
+
+fn compare_keys<K: Ord>(a: &Option<K>, b: &Option<K>) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn reorder_children<T: RelationshipTarget>(target: &mut T, order: impl IntoIterator<Item = Entity>)
+where
+    T::Collection: OrderedRelationshipSourceCollection,
+{
+    let collection = target.collection_mut_risky();
+    for (index, child) in order.into_iter().enumerate() {
+        collection.place(child, index);
+    }
+}
+
+/// A [`Plugin`] which keeps the children of all entities in a [`Relationship`] hierarchy sorted by component `K`.
+///
+/// Children are re-sorted whenever `K` changes on any of them, or when a child is added.
+/// The sort is stable, and children without `K` are placed last.
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::hierarchy::SortChildrenPlugin;
+///
+/// #[derive(Component, PartialEq, Eq, PartialOrd, Ord)]
+/// struct Order(i32);
+///
+/// let mut app = App::new();
+/// app.add_plugins(SortChildrenPlugin::<Order>::default());
+///
+/// let parent = app.world_mut().spawn_empty().id();
+/// let a = app.world_mut().spawn((Order(2), ChildOf(parent))).id();
+/// let b = app.world_mut().spawn((Order(1), ChildOf(parent))).id();
+/// app.update();
+///
+/// assert_eq!(**app.world().get::<Children>(parent).unwrap(), [b, a]);
+/// ```
+pub struct SortChildrenPlugin<K: Component + Ord, R: Relationship = ChildOf>(PhantomData<(K, R)>);
+
+impl<K: Component + Ord, R: Relationship> Default for SortChildrenPlugin<K, R> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<K: Component + Ord, R: Relationship> Plugin for SortChildrenPlugin<K, R>
+where
+    <R::RelationshipTarget as RelationshipTarget>::Collection: OrderedRelationshipSourceCollection,
+{
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, sort_children::<K, R>);
+    }
+}
+
+/// A [`System`] which sorts the children of all entities whose children have changed `K`.
+///
+/// See [`SortChildrenPlugin`] for more information.
+#[allow(clippy::type_complexity)]
+pub fn sort_children<K: Component + Ord, R: Relationship>(
+    changed: Query<&R, Or<(Changed<K>, Changed<R>)>>,
+    mut removed: RemovedComponents<K>,
+    parents: Query<&R>,
+    keys: Query<&K>,
+    mut targets: Query<&mut R::RelationshipTarget>,
+) where
+    <R::RelationshipTarget as RelationshipTarget>::Collection: OrderedRelationshipSourceCollection,
+{
+    let mut dirty: HashSet<Entity> = changed.iter().map(|parent| parent.get()).collect();
+    dirty.extend(
+        removed
+            .read()
+            .filter_map(|entity| parents.get(entity).ok())
+            .map(|parent| parent.get()),
+    );
+
+    for parent in dirty {
+        let Ok(target) = targets.get(parent) else {
+            continue;
+        };
+
+        let children: Vec<Entity> = target.iter().collect();
+        let mut sorted: Vec<(Option<&K>, Entity)> = children
+            .iter()
+            .map(|&child| (keys.get(child).ok(), child))
+            .collect();
+        sorted.sort_by(|(a, _), (b, _)| compare_keys(a, b));
+
+        if sorted.iter().map(|(_, child)| *child).eq(children) {
+            continue;
+        }
+
+        let mut target = targets.get_mut(parent).unwrap();
+        reorder_children(&mut *target, sorted.into_iter().map(|(_, child)| child));
+    }
+}
+
+/// A set of [`Relationship`] types which may be traversed together as a graph.
+///
+/// This trait is implemented for tuples of up to 8 relationship types, such as `(ChildOf, OwnedBy)`.
+///
+/// See [`GraphQuery`] for more information.
+pub trait RelationshipSet: Static {
+    /// The [`QueryData`] used to access the relationship targets of each entity.
+    type Targets: ReadOnlyQueryData + 'static;
+
+    /// Calls the given function for each entity related to an entity, given its relationship targets.
+    fn for_each_edge(
+        targets: ROQueryItem<'_, '_, Self::Targets>,
+        f: &mut dyn FnMut(Entity, GraphEdge),
+    );
+
+    /// Calls the given function for each entity related to the given `entity`.
+    fn for_each_world_edge(world: &World, entity: Entity, f: &mut dyn FnMut(Entity, GraphEdge));
+}
+
+macro_rules! impl_relationship_set {
+    ($(($t:ident, $i:tt)),*) => {
+        impl<$($t: Relationship),*> RelationshipSet for ($($t,)*) {
+            type Targets = ($(Option<&'static $t::RelationshipTarget>,)*);
+
+            fn for_each_edge(
+                targets: ROQueryItem<'_, '_, Self::Targets>,
+                f: &mut dyn FnMut(Entity, GraphEdge),
+            ) {
+                $(
+                    if let Some(target) = targets.$i {
+                        for entity in target.iter() {
+                            f(entity, GraphEdge::of::<$t>($i));
+                        }
+                    }
+                )*
+            }
+
+            fn for_each_world_edge(
+                world: &World,
+                entity: Entity,
+                f: &mut dyn FnMut(Entity, GraphEdge),
+            ) {
+                $(
+                    if let Some(target) = world.get::<$t::RelationshipTarget>(entity) {
+                        for entity in target.iter() {
+                            f(entity, GraphEdge::of::<$t>($i));
+                        }
+                    }
+                )*
+            }
+        }
+    };
+}
+
+impl_relationship_set!((A, 0));
+impl_relationship_set!((A, 0), (B, 1));
+impl_relationship_set!((A, 0), (B, 1), (C, 2));
+impl_relationship_set!((A, 0), (B, 1), (C, 2), (D, 3));
+impl_relationship_set!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4));
+impl_relationship_set!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4), (F, 5));
+impl_relationship_set!((A, 0), (B, 1), (C, 2), (D, 3), (E, 4), (F, 5), (G, 6));
+impl_relationship_set!(
+    (A, 0),
+    (B, 1),
+    (C, 2),
+    (D, 3),
+    (E, 4),
+    (F, 5),
+    (G, 6),
+    (H, 7)
+);
+
+/// The type of an edge in a [`RelationshipSet`] graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GraphEdge {
+    index: usize,
+    type_id: TypeId,
+}
+
+impl GraphEdge {
+    fn of<R: Relationship>(index: usize) -> Self {
+        Self {
+            index,
+            type_id: TypeId::of::<R>(),
+        }
+    }
+
+    /// Returns the index of the relationship type of this edge within its [`RelationshipSet`].
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns `true` if this edge is of the given [`Relationship`] type.
+    pub fn is<R: Relationship>(&self) -> bool {
+        self.type_id == TypeId::of::<R>()
+    }
+}
+
+/// A single step of a [`RelationshipSet`] graph traversal.
+///
+/// See [`GraphQuery`] for more information.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GraphStep {
+    /// The visited entity.
+    pub entity: Entity,
+    /// The entity from which the visited entity was reached.
+    pub source: Entity,
+    /// The type of the edge which was followed to reach the visited entity.
+    pub edge: GraphEdge,
+    /// The number of edges between the traversal root and the visited entity.
+    pub depth: usize,
+}
+
+/// A [`SystemParam`] for traversal of entities linked by multiple [`Relationship`] types.
+///
+/// # Usage
+///
+/// Each traversal starts at a given entity and follows the relationship targets of all types in the given
+/// [`RelationshipSet`], such as all entities which are children of, or owned by, the starting entity.
+///
+/// Each entity is visited at most once, even if it is reachable through multiple edges.
+/// Each visit is returned as a [`GraphStep`] which reports the edge through which the entity was reached.
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::prelude::*;
+/// use moonshine_util::hierarchy::GraphQuery;
+///
+/// relationship! {
+///     #[derive(Component)]
+///     pub struct Owned(Vec<Entity>) -> {
+///         #[derive(Component)]
+///         pub struct OwnedBy(pub Entity)
+///     }
+/// }
+///
+/// fn owned_items(
+///     player: In<Entity>,
+///     graph: GraphQuery<(ChildOf, OwnedBy)>,
+/// ) -> Vec<Entity> {
+///     // Every owned item reachable from the player, including items owned by its children:
+///     graph
+///         .traverse_wide(*player)
+///         .filter(|step| step.edge.is::<OwnedBy>())
+///         .map(|step| step.entity)
+///         .collect()
+/// }
+/// # bevy_ecs::system::assert_is_system(owned_items);
+/// ```
+#[derive(SystemParam)]
+pub struct GraphQuery<'w, 's, T: RelationshipSet> {
+    targets: Query<'w, 's, <T as RelationshipSet>::Targets>,
+}
+
+impl<T: RelationshipSet> GraphQuery<'_, '_, T> {
+    /// Returns all entities directly related to the given `entity`, along with the type of each edge.
+    pub fn edges(&self, entity: Entity) -> Vec<(Entity, GraphEdge)> {
+        let mut edges = Vec::new();
+        if let Ok(targets) = self.targets.get(entity) {
+            T::for_each_edge(targets, &mut |target, edge| edges.push((target, edge)));
+        }
+        edges
+    }
+
+    /// Iterates over all entities reachable from the given `entity` in breadth-first order.
+    pub fn traverse_wide(&self, entity: Entity) -> impl Iterator<Item = GraphStep> + '_ {
+        traverse_graph_wide_with(entity, move |entity| self.edges(entity))
+    }
+
+    /// Iterates over all entities reachable from the given `entity` in depth-first order.
+    pub fn traverse_deep(&self, entity: Entity) -> impl Iterator<Item = GraphStep> + '_ {
+        traverse_graph_deep_with(entity, move |entity| self.edges(entity))
+    }
+}
+
+/// Computes a post-order reduction of the given `entity` and all its descendants in a [`Relationship`] hierarchy.
+///
+/// See [`HierarchyQuery::fold_subtree`] for more information.
+///
+/// Note that if the query data accesses any component which is not registered in the world, no entity matches it.
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::hierarchy::fold_subtree;
+///
+/// #[derive(Component)]
+/// struct Weight(f32);
+///
+/// let mut world = World::new();
+/// let a = world.spawn(Weight(1.0)).id();
+/// let b = world.spawn((Weight(2.0), ChildOf(a))).id();
+/// world.spawn((Weight(3.0), ChildOf(b)));
+/// world.spawn(ChildOf(a));
+///
+/// let total = fold_subtree::<ChildOf, &Weight, f32>(&world, a, |_, weight, children| {
+///     weight.map_or(0.0, |weight| weight.0) + children.iter().sum::<f32>()
+/// });
+/// assert_eq!(total, 6.0);
+/// ```
+pub fn fold_subtree<R: Relationship, D: ReadOnlyQueryData + 'static, T>(
+    world: &World,
+    entity: Entity,
+    mut f: impl FnMut(Entity, Option<ROQueryItem<'_, '_, D>>, Vec<T>) -> T,
+) -> T {
+    let query = world.try_query::<D>();
+    fold_subtree_with(
+        entity,
+        |entity| world_children::<R>(world, entity),
+        |entity, children| {
+            let data = query
+                .as_ref()
+                .and_then(|query| query.get_manual(world, entity).ok());
+            f(entity, data, children)
+        },
+    )
+}
+
+fn fold_subtree_with<T>(
+    root: Entity,
+    mut children: impl FnMut(Entity) -> Vec<Entity>,
+    mut f: impl FnMut(Entity, Vec<T>) -> T,
+) -> T {
+    enum Step {
+        Enter(Entity),
+        Exit(Entity, usize),
+    }
+
+    let mut stack = vec![Step::Enter(root)];
+    let mut results = Vec::new();
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Enter(entity) => {
+                let children = children(entity);
+                stack.push(Step::Exit(entity, children.len()));
+                stack.extend(children.into_iter().rev().map(Step::Enter));
+            }
+            Step::Exit(entity, count) => {
+                let children = results.split_off(results.len() - count);
+                results.push(f(entity, children));
+            }
+        }
+    }
+
+    results.pop().unwrap()
+}
+
+/// Iterates over all entities reachable from the given `entity` through a [`RelationshipSet`] in breadth-first order.
+///
+/// See [`GraphQuery`] for more information.
+pub fn traverse_graph_wide<T: RelationshipSet>(
+    world: &World,
+    entity: Entity,
+) -> impl Iterator<Item = GraphStep> + '_ {
+    traverse_graph_wide_with(entity, move |entity| world_edges::<T>(world, entity))
+}
+
+/// Iterates over all entities reachable from the given `entity` through a [`RelationshipSet`] in depth-first order.
+///
+/// See [`GraphQuery`] for more information.
+pub fn traverse_graph_deep<T: RelationshipSet>(
+    world: &World,
+    entity: Entity,
+) -> impl Iterator<Item = GraphStep> + '_ {
+    traverse_graph_deep_with(entity, move |entity| world_edges::<T>(world, entity))
+}
+
+fn world_edges<T: RelationshipSet>(world: &World, entity: Entity) -> Vec<(Entity, GraphEdge)> {
+    let mut edges = Vec::new();
+    T::for_each_world_edge(world, entity, &mut |target, edge| {
+        edges.push((target, edge))
+    });
+    edges
+}
+
+fn graph_steps(
+    source: Entity,
+    depth: usize,
+    edges: Vec<(Entity, GraphEdge)>,
+) -> impl DoubleEndedIterator<Item = GraphStep> {
+    edges.into_iter().map(move |(entity, edge)| GraphStep {
+        entity,
+        source,
+        edge,
+        depth: depth + 1,
+    })
+}
+
+fn traverse_graph_wide_with(
+    root: Entity,
+    mut edges: impl FnMut(Entity) -> Vec<(Entity, GraphEdge)>,
+) -> impl Iterator<Item = GraphStep> {
+    let mut visited: HashSet<Entity> = HashSet::from_iter([root]);
+    let mut queue: VecDeque<GraphStep> = graph_steps(root, 0, edges(root))
+        .filter(|step| visited.insert(step.entity))
+        .collect();
+    std::iter::from_fn(move || {
+        let current = queue.pop_front()?;
+        queue.extend(
+            graph_steps(current.entity, current.depth, edges(current.entity))
+                .filter(|step| visited.insert(step.entity)),
+        );
+        Some(current)
+    })
+}
+
+fn traverse_graph_deep_with(
+    root: Entity,
+    mut edges: impl FnMut(Entity) -> Vec<(Entity, GraphEdge)>,
+) -> impl Iterator<Item = GraphStep> {
+    let mut visited: HashSet<Entity> = HashSet::from_iter([root]);
+    let mut stack: Vec<GraphStep> = graph_steps(root, 0, edges(root)).rev().collect();
+    std::iter::from_fn(move || loop {
+        let current = stack.pop()?;
+        if !visited.insert(current.entity) {
+            continue;
+        }
+
+        stack.extend(
+            graph_steps(current.entity, current.depth, edges(current.entity))
+                .rev()
+                .filter(|step| !visited.contains(&step.entity)),
+        );
+        return Some(current);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn parent_child() {
+        let mut w = World::new();
+        let e = w
+            .spawn_empty()
+            .with_children(|parent| {
+                parent.spawn_empty();
+            })
+            .id();
+
+        let pass = w.run_system_once(move |q: HierarchyQuery| {
+            assert_eq!(q.children(e).count(), 1);
+            true
+        });
+
+        assert!(pass.unwrap());
+    }
+
+    #[test]
+    fn ancestors() {
+        #[derive(Component)]
+        struct A(usize);
+
+        #[derive(Component)]
+        struct X;
+
+        let mut w = World::new();
+        w.spawn(A(0)).with_children(|a| {
+            a.spawn(A(1)).with_children(|b| {
+                b.spawn(A(2)).with_children(|c| {
+                    c.spawn(A(3));
+                    c.spawn(A(4)).with_children(|d| {
+                        d.spawn(A(5));
+                    });
+                });
+                b.spawn(A(6)).with_children(|c| {
+                    c.spawn((A(7), X));
+                });
+            });
+        });
+
+        let r = w
+            .run_system_once(
+                move |q: HierarchyQuery, qa: Query<&A>, x: Single<Entity, With<X>>| {
+                    let mut r = Vec::new();
+                    for e in q.ancestors(*x) {
+                        r.push(qa.get(e).unwrap().0);
+                    }
+                    r
+                },
+            )
+            .unwrap();
+
+        assert_eq!(r, vec![6, 1, 0]);
+    }
+
+    #[test]
+    fn descendants_wide() {
+        #[derive(Component)]
+        struct A(usize);
+
+        let mut w = World::new();
+        let entity = w
+            .spawn(A(0))
+            .with_children(|a| {
+                a.spawn(A(1)).with_children(|b| {
+                    b.spawn(A(2)).with_children(|c| {
+                        c.spawn(A(3));
+                        c.spawn(A(4)).with_children(|d| {
+                            d.spawn(A(5));
+                        });
+                    });
+                    b.spawn(A(6)).with_children(|c| {
+                        c.spawn(A(7));
+                    });
+                });
+            })
+            .id();
+
+        let r = w
+            .run_system_once(move |q: HierarchyQuery, qa: Query<&A>| {
+                let mut r = Vec::new();
+                for e in q.descendants_wide(entity) {
+                    r.push(qa.get(e).unwrap().0);
+                }
+                r
+            })
+            .unwrap();
+
+        assert_eq!(r, vec![1, 2, 6, 3, 4, 7, 5]);
     }
 
     #[test]
-    fn ancestors() {
+    fn world_descendants_wide() {
+        #[derive(Component)]
+        struct A(usize);
+
+        let mut w = World::new();
+        let entity = w
+            .spawn(A(0))
+            .with_children(|a| {
+                a.spawn(A(1)).with_children(|b| {
+                    b.spawn(A(2)).with_children(|c| {
+                        c.spawn(A(3));
+                        c.spawn(A(4)).with_children(|d| {
+                            d.spawn(A(5));
+                        });
+                    });
+                    b.spawn(A(6)).with_children(|c| {
+                        c.spawn(A(7));
+                    });
+                });
+            })
+            .id();
+
+        let r: Vec<_> = WorldDescendantsWideIter::<ChildOf>::new(&w, entity)
+            .filter_map(|entity| w.get::<A>(entity))
+            .map(|&A(v)| v)
+            .collect();
+
+        assert_eq!(r, vec![1, 2, 6, 3, 4, 7, 5]);
+    }
+
+    #[test]
+    fn world_descendants_missing() {
+        let mut w = World::new();
+        let a = w.spawn_empty().id();
+        let b = w.spawn(ChildOf(a)).id();
+        let c = w.spawn_empty().id();
+        w.despawn(c);
+        w.get_mut::<Children>(a)
+            .unwrap()
+            .collection_mut_risky()
+            .push(c);
+
+        let wide: Vec<_> = WorldDescendantsWideIter::<ChildOf>::new(&w, a).collect();
+        let deep: Vec<_> = WorldDescendantsDeepIter::<ChildOf>::new(&w, a).collect();
+
+        assert_eq!(wide, [b, c]);
+        assert_eq!(deep, [b, c]);
+    }
+
+    #[test]
+    fn descendants_deep() {
+        #[derive(Component)]
+        struct A(usize);
+
+        let mut w = World::new();
+        let entity = w
+            .spawn(A(0))
+            .with_children(|a| {
+                a.spawn(A(1)).with_children(|b| {
+                    b.spawn(A(2)).with_children(|c| {
+                        c.spawn(A(3));
+                        c.spawn(A(4)).with_children(|d| {
+                            d.spawn(A(5));
+                        });
+                    });
+                    b.spawn(A(6)).with_children(|c| {
+                        c.spawn(A(7));
+                    });
+                });
+            })
+            .id();
+
+        let r = w
+            .run_system_once(move |q: HierarchyQuery, qa: Query<&A>| {
+                let mut r = Vec::new();
+                for e in q.descendants_deep(entity) {
+                    r.push(qa.get(e).unwrap().0);
+                }
+                r
+            })
+            .unwrap();
+
+        assert_eq!(r, vec![1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn world_descendants_deep() {
+        #[derive(Component)]
+        struct A(usize);
+
+        let mut w = World::new();
+        let entity = w
+            .spawn(A(0))
+            .with_children(|a| {
+                a.spawn(A(1)).with_children(|b| {
+                    b.spawn(A(2)).with_children(|c| {
+                        c.spawn(A(3));
+                        c.spawn(A(4)).with_children(|d| {
+                            d.spawn(A(5));
+                        });
+                    });
+                    b.spawn(A(6)).with_children(|c| {
+                        c.spawn(A(7));
+                    });
+                });
+            })
+            .id();
+
+        let r: Vec<_> = WorldDescendantsDeepIter::<ChildOf>::new(&w, entity)
+            .filter_map(|entity| w.get::<A>(entity))
+            .map(|&A(v)| v)
+            .collect();
+
+        assert_eq!(r, vec![1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn links() {
+        use crate::component::LinkEntities;
+
+        struct Follows;
+
+        let mut w = World::new();
+        let a = w.spawn_empty().id();
+        let b = w.spawn(Links::<Follows>::new([a])).id();
+        let c = w.spawn(Links::<Follows>::new([a, b])).id();
+        let d = w.spawn(Links::<Follows>::new([c])).id();
+        w.entity_mut(a).link::<Follows>(d);
+
+        let r = w
+            .run_system_once(move |q: LinkQuery<Follows>| {
+                (
+                    q.parents(c).collect::<Vec<_>>(),
+                    q.children(a).collect::<Vec<_>>(),
+                    q.ancestors(d).collect::<Vec<_>>(),
+                    q.descendants_wide(a).collect::<Vec<_>>(),
+                    q.descendants_deep(a).collect::<Vec<_>>(),
+                )
+            })
+            .unwrap();
+
+        assert_eq!(r.0, [a, b]);
+        assert_eq!(r.1, [b, c]);
+        assert_eq!(r.2, [c, a, b]);
+        assert_eq!(r.3, [b, c, d]);
+        assert_eq!(r.4, [b, c, d]);
+
+        w.entity_mut(c).despawn();
+        assert!(w.get::<Links<Follows>>(d).is_none());
+        assert!(w.get::<LinkedBy<Follows>>(b).is_none());
+        assert_eq!(
+            w.get::<LinkedBy<Follows>>(a)
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            [b]
+        );
+
+        w.entity_mut(a).unlink::<Follows>(d);
+        assert!(w.get::<Links<Follows>>(a).is_none());
+        assert!(w.get::<LinkedBy<Follows>>(d).is_none());
+
+        w.entity_mut(a).link::<Follows>(a);
+        assert!(w.get::<Links<Follows>>(a).is_none());
+    }
+
+    #[test]
+    fn edges() {
+        crate::relationship! {
+            #[derive(Component)]
+            pub struct Slots(Vec<Entity>) -> {
+                #[derive(Component)]
+                pub struct ChildOfSlot {
+                    #[relationship]
+                    pub parent: Entity,
+                    pub slot: u8,
+                }
+            }
+        }
+
+        let mut w = World::new();
+        let a = w.spawn_empty().id();
+        let b = w.spawn_empty().id();
+        let c = w.spawn(ChildOfSlot { parent: a, slot: 1 }).id();
+        let d = w.spawn(ChildOfSlot { parent: a, slot: 2 }).id();
+
+        let edges = move |w: &mut World| {
+            w.run_system_once(move |q: HierarchyQuery<ChildOfSlot>| {
+                (
+                    q.child_edges(a)
+                        .map(|(child, edge)| (child, edge.slot))
+                        .collect::<Vec<_>>(),
+                    q.child_edges(b)
+                        .map(|(child, edge)| (child, edge.slot))
+                        .collect::<Vec<_>>(),
+                    q.child_edge(a, d).map(|edge| edge.slot),
+                )
+            })
+            .unwrap()
+        };
+
+        assert_eq!(edges(&mut w), (vec![(c, 1), (d, 2)], vec![], Some(2)));
+
+        w.entity_mut(d).insert(ChildOfSlot { parent: b, slot: 3 });
+        assert_eq!(edges(&mut w), (vec![(c, 1)], vec![(d, 3)], None));
+
+        w.entity_mut(c).despawn();
+        assert_eq!(edges(&mut w), (vec![], vec![(d, 3)], None));
+    }
+
+    #[test]
+    fn descendants_filtered() {
         #[derive(Component)]
         struct A(usize);
 
-        #[derive(Component)]
-        struct X;
+        #[derive(Component)]
+        struct Skip;
+
+        #[derive(Component)]
+        struct Prune;
+
+        let mut w = World::new();
+        let entity = w
+            .spawn(A(0))
+            .with_children(|a| {
+                a.spawn((A(1), Skip)).with_children(|b| {
+                    b.spawn(A(2)).with_children(|c| {
+                        c.spawn(A(3));
+                        c.spawn((A(4), Prune)).with_children(|d| {
+                            d.spawn(A(5));
+                        });
+                    });
+                    b.spawn((A(6), Skip, Prune)).with_children(|c| {
+                        c.spawn(A(7));
+                    });
+                });
+            })
+            .id();
+
+        let r = w
+            .run_system_once(
+                move |q: HierarchyQuery,
+                      qa: Query<&A>,
+                      skip: Query<(), With<Skip>>,
+                      prune: Query<(), With<Prune>>| {
+                    let wide: Vec<_> = q
+                        .descendants_wide_filtered(
+                            entity,
+                            |e| !skip.contains(e),
+                            |e| prune.contains(e),
+                        )
+                        .map(|e| qa.get(e).unwrap().0)
+                        .collect();
+                    let deep: Vec<_> = q
+                        .descendants_deep_filtered(
+                            entity,
+                            |e| !skip.contains(e),
+                            |e| prune.contains(e),
+                        )
+                        .map(|e| qa.get(e).unwrap().0)
+                        .collect();
+                    (wide, deep)
+                },
+            )
+            .unwrap();
+
+        assert_eq!(r.0, vec![2, 3, 4]);
+        assert_eq!(r.1, vec![2, 3, 4]);
+
+        let wide: Vec<_> = WorldDescendantsWideIter::<ChildOf>::new(&w, entity)
+            .visit(|e| !e.contains::<Skip>())
+            .prune(|e| e.contains::<Prune>())
+            .filter_map(|entity| w.get::<A>(entity))
+            .map(|&A(v)| v)
+            .collect();
+
+        let deep: Vec<_> = WorldDescendantsDeepIter::<ChildOf>::new(&w, entity)
+            .visit(|e| !e.contains::<Skip>())
+            .prune(|e| e.contains::<Prune>())
+            .filter_map(|entity| w.get::<A>(entity))
+            .map(|&A(v)| v)
+            .collect();
+
+        assert_eq!(wide, vec![2, 3, 4]);
+        assert_eq!(deep, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn tree_queries() {
+        let mut w = World::new();
+        let a = w.spawn_empty().id();
+        let b = w.spawn(ChildOf(a)).id();
+        let c = w.spawn(ChildOf(b)).id();
+        let d = w.spawn(ChildOf(b)).id();
+        let e = w.spawn(ChildOf(d)).id();
+        let x = w.spawn_empty().id();
+
+        w.run_system_once(move |q: HierarchyQuery| {
+            assert_eq!(q.root(e), a);
+            assert_eq!(q.root(a), a);
+            assert_eq!(q.depth(a), 0);
+            assert_eq!(q.depth(e), 3);
+
+            assert!(q.is_ancestor_of(b, e));
+            assert!(!q.is_ancestor_of(e, b));
+            assert!(!q.is_ancestor_of(c, e));
+            assert!(q.is_descendant_of(e, a));
+            assert!(!q.is_descendant_of(a, a));
+
+            assert_eq!(q.lowest_common_ancestor(c, e), Some(b));
+            assert_eq!(q.lowest_common_ancestor(b, e), Some(b));
+            assert_eq!(q.lowest_common_ancestor(e, e), Some(e));
+            assert_eq!(q.lowest_common_ancestor(e, x), None);
+
+            assert_eq!(q.path_between(c, e), Some(vec![c, b, d, e]));
+            assert_eq!(q.path_between(e, a), Some(vec![e, d, b, a]));
+            assert_eq!(q.path_between(a, c), Some(vec![a, b, c]));
+            assert_eq!(q.path_between(c, c), Some(vec![c]));
+            assert_eq!(q.path_between(c, x), None);
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn tree_queries_cyclic() {
+        let mut w = World::new();
+        let a = w.spawn_empty().id();
+        let b = w.spawn(ChildOf(a)).id();
+        let c = w.spawn(ChildOf(b)).id();
+        let x = w.spawn_empty().id();
+        w.entity_mut(a).insert(ChildOf(c));
+
+        w.run_system_once(move |q: HierarchyQuery| {
+            assert_eq!(q.root(a), b);
+            assert_eq!(q.depth(a), 2);
+            assert!(q.is_ancestor_of(b, a));
+            assert!(!q.is_ancestor_of(x, a));
+            assert_eq!(q.lowest_common_ancestor(a, b), Some(b));
+            assert_eq!(q.lowest_common_ancestor(a, x), None);
+            assert_eq!(q.path_between(a, x), None);
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn paths() {
+        let mut w = World::new();
+        let body = w.spawn(Name::new("Body")).id();
+        let arm_l = w.spawn((Name::new("Arm.L"), ChildOf(body))).id();
+        let arm_r = w.spawn((Name::new("Arm.R"), ChildOf(body))).id();
+        let hand_l = w.spawn((Name::new("Hand"), ChildOf(arm_l))).id();
+        let hand_r = w.spawn((Name::new("Hand"), ChildOf(arm_r))).id();
+        let finger_a = w.spawn((Name::new("Finger"), ChildOf(hand_l))).id();
+        let finger_b = w.spawn((Name::new("Finger"), ChildOf(hand_l))).id();
+        let unnamed = w.spawn(ChildOf(hand_l)).id();
+
+        w.run_system_once(move |q: HierarchyPathQuery| {
+            assert_eq!(q.find_by_path(body, "Arm.L/Hand"), Some(hand_l));
+            assert_eq!(q.find_by_path(body, "Arm.R/Hand"), Some(hand_r));
+            assert_eq!(q.find_by_path(body, "Arm.L/Hand/Finger[1]"), Some(finger_b));
+            assert_eq!(q.find_by_path(body, "Arm.L/Hand/Finger[2]"), None);
+            assert_eq!(q.find_by_path(body, "Leg"), None);
+            assert_eq!(q.find_by_path(hand_l, "../../Arm.R/./Hand"), Some(hand_r));
+            assert_eq!(q.find_by_path(hand_l, ""), Some(hand_l));
+            assert_eq!(q.find_all_by_path(body, "Arm.*/Hand"), [hand_l, hand_r]);
+            assert_eq!(q.find_all_by_path(body, "*/*"), [hand_l, hand_r]);
+            assert_eq!(
+                q.find_all_by_path(body, "*/*/*"),
+                [finger_a, finger_b, unnamed]
+            );
+            assert_eq!(q.find_all_by_path(body, "*/*/F*r"), [finger_a, finger_b]);
+            assert_eq!(q.find_all_by_path(hand_l, "Finger/.."), [hand_l]);
+
+            assert_eq!(q.entity_path(body).unwrap(), "");
+            assert_eq!(q.entity_path(hand_r).unwrap(), "Arm.R/Hand");
+            assert_eq!(q.entity_path(finger_b).unwrap(), "Arm.L/Hand/Finger[1]");
+            assert_eq!(q.entity_path(unnamed).unwrap(), "Arm.L/Hand/*[2]");
+            assert_eq!(q.entity_path_from(arm_l, finger_a).unwrap(), "Hand/Finger");
+            assert_eq!(q.entity_path_from(arm_r, finger_a), None);
+
+            for entity in [hand_l, finger_a, finger_b, unnamed] {
+                let path = q.entity_path(entity).unwrap();
+                assert_eq!(q.find_by_path(body, &path), Some(entity));
+            }
+        })
+        .unwrap();
+
+        assert_eq!(
+            find_by_path::<ChildOf>(&w, body, "Arm.L/Hand/*[2]"),
+            Some(unnamed)
+        );
+        assert_eq!(
+            find_all_by_path::<ChildOf>(&w, body, "Arm.*"),
+            [arm_l, arm_r]
+        );
+        assert_eq!(
+            entity_path::<ChildOf>(&w, finger_b).unwrap(),
+            "Arm.L/Hand/Finger[1]"
+        );
+        assert_eq!(
+            entity_path_from::<ChildOf>(&w, hand_l, finger_b).unwrap(),
+            "Finger[1]"
+        );
+    }
+
+    #[test]
+    fn paths_special_names() {
+        use bevy_ecs::relationship::RelationshipHookMode;
+
+        let mut w = World::new();
+        let root = w.spawn_empty().id();
+        let names = ["AB", "A*", "x/y", "..", ".", "", "Hand[0]"];
+        let entities: Vec<Entity> = names
+            .iter()
+            .map(|&name| w.spawn((Name::new(name), ChildOf(root))).id())
+            .collect();
+
+        assert_eq!(entity_path::<ChildOf>(&w, entities[0]).unwrap(), "AB");
+        assert_eq!(entity_path::<ChildOf>(&w, entities[1]).unwrap(), "*[1]");
+        for entity in entities {
+            let path = entity_path::<ChildOf>(&w, entity).unwrap();
+            assert_eq!(find_by_path::<ChildOf>(&w, root, &path), Some(entity));
+        }
+
+        let unlisted = w.spawn_empty().id();
+        w.entity_mut(unlisted)
+            .insert_with_relationship_hook_mode(ChildOf(root), RelationshipHookMode::Skip);
+        assert_eq!(entity_path::<ChildOf>(&w, unlisted), None);
+    }
+
+    #[test]
+    fn glob() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("Arm.*", "Arm.L"));
+        assert!(glob_match("*.L", "Arm.L"));
+        assert!(glob_match("A*m*L", "Arm.L"));
+        assert!(glob_match("a*a", "aa"));
+        assert!(!glob_match("a*a", "a"));
+        assert!(!glob_match("Arm", "Arm.L"));
+        assert!(!glob_match("*.R", "Arm.L"));
+    }
+
+    #[test]
+    fn siblings() {
+        let mut w = World::new();
+        let a = w.spawn_empty().id();
+        let b = w.spawn(ChildOf(a)).id();
+        let c = w.spawn(ChildOf(a)).id();
+        let d = w.spawn(ChildOf(a)).id();
+
+        w.run_system_once(move |q: HierarchyQuery| {
+            assert_eq!(q.siblings(c).collect::<Vec<_>>(), [b, d]);
+            assert_eq!(q.siblings(a).count(), 0);
+            assert_eq!(q.sibling_index(d), Some(2));
+            assert_eq!(q.sibling_index(a), None);
+            assert_eq!(q.next_sibling(b), Some(c));
+            assert_eq!(q.next_sibling(d), None);
+            assert_eq!(q.prev_sibling(c), Some(b));
+            assert_eq!(q.prev_sibling(b), None);
+        })
+        .unwrap();
+
+        assert_eq!(
+            super::siblings::<ChildOf>(&w, c).collect::<Vec<_>>(),
+            [b, d]
+        );
+        assert_eq!(super::siblings::<ChildOf>(&w, a).count(), 0);
+        assert_eq!(sibling_index::<ChildOf>(&w, d), Some(2));
+        assert_eq!(sibling_index::<ChildOf>(&w, a), None);
+        assert_eq!(next_sibling::<ChildOf>(&w, b), Some(c));
+        assert_eq!(next_sibling::<ChildOf>(&w, d), None);
+        assert_eq!(prev_sibling::<ChildOf>(&w, c), Some(b));
+        assert_eq!(prev_sibling::<ChildOf>(&w, b), None);
+    }
+
+    #[test]
+    fn traverse() {
+        let mut w = World::new();
+        let a = w.spawn_empty().id();
+        let b = w.spawn(ChildOf(a)).id();
+        let c = w.spawn(ChildOf(b)).id();
+        let d = w.spawn(ChildOf(b)).id();
+        let e = w.spawn(ChildOf(d)).id();
+        let f = w.spawn(ChildOf(a)).id();
+
+        let item = |entity, parent, depth, index| TraversalItem {
+            entity,
+            parent,
+            depth,
+            index,
+        };
+
+        let wide = vec![
+            item(b, a, 1, 0),
+            item(f, a, 1, 1),
+            item(c, b, 2, 0),
+            item(d, b, 2, 1),
+            item(e, d, 3, 0),
+        ];
+
+        let deep = vec![
+            item(b, a, 1, 0),
+            item(c, b, 2, 0),
+            item(d, b, 2, 1),
+            item(e, d, 3, 0),
+            item(f, a, 1, 1),
+        ];
+
+        let r = w
+            .run_system_once(move |q: HierarchyQuery| {
+                (
+                    q.traverse_wide(a, None).collect::<Vec<_>>(),
+                    q.traverse_deep(a, None).collect::<Vec<_>>(),
+                    q.traverse_wide(a, Some(2)).collect::<Vec<_>>(),
+                    q.traverse_deep(a, Some(1)).collect::<Vec<_>>(),
+                    q.traverse_wide(a, Some(0)).count(),
+                    q.traverse_deep(a, Some(0)).count(),
+                )
+            })
+            .unwrap();
+
+        assert_eq!(r.0, wide);
+        assert_eq!(r.1, deep);
+        assert_eq!(r.2, wide[..4]);
+        assert_eq!(r.3, [deep[0], deep[4]]);
+        assert_eq!(r.4, 0);
+        assert_eq!(r.5, 0);
+
+        let world_wide: Vec<_> = WorldDescendantsWideIter::<ChildOf>::new(&w, a)
+            .items()
+            .collect();
+        let world_deep: Vec<_> = WorldDescendantsDeepIter::<ChildOf>::new(&w, a)
+            .items()
+            .collect();
+        let world_deep_limited: Vec<_> = WorldDescendantsDeepIter::<ChildOf>::new(&w, a)
+            .max_depth(2)
+            .collect();
+
+        assert_eq!(world_wide, wide);
+        assert_eq!(world_deep, deep);
+        assert_eq!(world_deep_limited, [b, c, d, f]);
+
+        let world_wide_empty = WorldDescendantsWideIter::<ChildOf>::new(&w, a)
+            .max_depth(0)
+            .count();
+        let world_deep_empty = WorldDescendantsDeepIter::<ChildOf>::new(&w, a)
+            .max_depth(0)
+            .count();
+
+        assert_eq!(world_wide_empty, 0);
+        assert_eq!(world_deep_empty, 0);
+    }
+
+    #[test]
+    fn visitor() {
+        #[derive(Default)]
+        struct Log {
+            events: Vec<(bool, Entity, usize)>,
+            skip: Option<Entity>,
+            stop: Option<Entity>,
+        }
+
+        impl HierarchyVisitor for Log {
+            fn enter(&mut self, entity: Entity, depth: usize) -> VisitControl {
+                self.events.push((true, entity, depth));
+                if self.skip == Some(entity) {
+                    VisitControl::SkipChildren
+                } else if self.stop == Some(entity) {
+                    VisitControl::Stop
+                } else {
+                    VisitControl::Continue
+                }
+            }
+
+            fn exit(&mut self, entity: Entity, depth: usize) {
+                self.events.push((false, entity, depth));
+            }
+        }
+
+        let mut w = World::new();
+        let a = w.spawn_empty().id();
+        let b = w.spawn(ChildOf(a)).id();
+        let c = w.spawn(ChildOf(b)).id();
+        let d = w.spawn(ChildOf(a)).id();
+
+        let mut log = Log::default();
+        assert!(visit_subtree::<ChildOf>(&w, a, &mut log));
+        assert_eq!(
+            log.events,
+            [
+                (true, a, 0),
+                (true, b, 1),
+                (true, c, 2),
+                (false, c, 2),
+                (false, b, 1),
+                (true, d, 1),
+                (false, d, 1),
+                (false, a, 0),
+            ]
+        );
+
+        let log = w
+            .run_system_once(move |q: HierarchyQuery| {
+                let mut log = Log {
+                    skip: Some(b),
+                    ..Default::default()
+                };
+                assert!(q.visit_subtree(a, &mut log));
+                log
+            })
+            .unwrap();
+        assert_eq!(
+            log.events,
+            [
+                (true, a, 0),
+                (true, b, 1),
+                (false, b, 1),
+                (true, d, 1),
+                (false, d, 1),
+                (false, a, 0),
+            ]
+        );
+
+        let mut log = Log {
+            stop: Some(c),
+            ..Default::default()
+        };
+        assert!(!visit_subtree::<ChildOf>(&w, a, &mut log));
+        assert_eq!(log.events, [(true, a, 0), (true, b, 1), (true, c, 2)]);
+    }
+
+    #[test]
+    fn validate() {
+        use bevy_ecs::relationship::{RelationshipHookMode, RelationshipTarget};
+
+        crate::relationship! {
+            #[derive(Component)]
+            pub struct Followers(Vec<Entity>) -> {
+                #[derive(Component)]
+                pub struct Follows(pub Entity)
+            }
+        }
 
         let mut w = World::new();
-        w.spawn(A(0)).with_children(|a| {
-            a.spawn(A(1)).with_children(|b| {
-                b.spawn(A(2)).with_children(|c| {
-                    c.spawn(A(3));
-                    c.spawn(A(4)).with_children(|d| {
-                        d.spawn(A(5));
-                    });
-                });
-                b.spawn(A(6)).with_children(|c| {
-                    c.spawn((A(7), X));
-                });
-            });
-        });
+        let a = w.spawn_empty().id();
+        let b = w.spawn(Follows(a)).id();
+        let c = w.spawn(Follows(b)).id();
+        assert!(validate_hierarchy::<Follows>(&w).is_valid());
 
-        let r = w
-            .run_system_once(
-                move |q: HierarchyQuery, qa: Query<&A>, x: Single<Entity, With<X>>| {
-                    let mut r = Vec::new();
-                    for e in q.ancestors(*x) {
-                        r.push(qa.get(e).unwrap().0);
-                    }
-                    r
+        w.entity_mut(a).insert(Follows(c));
+        let mut cycle = vec![a, c, b];
+        let min = (0..3).min_by_key(|&i| cycle[i]).unwrap();
+        cycle.rotate_left(min);
+        assert_eq!(
+            validate_hierarchy::<Follows>(&w).issues,
+            [HierarchyIssue::Cycle { entities: cycle }]
+        );
+        w.entity_mut(a).remove::<Follows>();
+
+        let d = w.spawn_empty().id();
+        w.entity_mut(d)
+            .insert_with_relationship_hook_mode(Follows(d), RelationshipHookMode::Skip);
+        assert_eq!(
+            validate_hierarchy::<Follows>(&w).issues,
+            [HierarchyIssue::SelfReference { entity: d }]
+        );
+        w.entity_mut(d).despawn();
+
+        let e = w.spawn_empty().id();
+        let f = w.spawn_empty().id();
+        w.entity_mut(e).despawn();
+        w.entity_mut(f)
+            .insert_with_relationship_hook_mode(Follows(e), RelationshipHookMode::Skip);
+        assert_eq!(
+            validate_hierarchy::<Follows>(&w).issues,
+            [HierarchyIssue::DanglingTarget {
+                source: f,
+                target: e
+            }]
+        );
+
+        w.entity_mut(f)
+            .insert_with_relationship_hook_mode(Follows(a), RelationshipHookMode::Skip);
+        assert_eq!(
+            validate_hierarchy::<Follows>(&w).issues,
+            [HierarchyIssue::UnlistedSource {
+                source: f,
+                target: a
+            }]
+        );
+
+        let mut followers = w.get_mut::<Followers>(a).unwrap();
+        followers.collection_mut_risky().push(f);
+        followers.collection_mut_risky().push(b);
+        followers.collection_mut_risky().push(c);
+        let report = validate_hierarchy::<Follows>(&w);
+        assert_eq!(
+            report.issues,
+            [
+                HierarchyIssue::DuplicateSource {
+                    target: a,
+                    source: b
                 },
-            )
+                HierarchyIssue::MismatchedSource {
+                    target: a,
+                    source: c
+                },
+            ]
+        );
+        assert_eq!(
+            report.issues[1].to_string(),
+            format!("entity {a} lists {c} as its source, but {c} is not related to it")
+        );
+    }
+
+    #[test]
+    fn cycle_safe() {
+        let mut w = World::new();
+        let a = w.spawn_empty().id();
+        let b = w.spawn(ChildOf(a)).id();
+        let c = w.spawn(ChildOf(b)).id();
+
+        let ancestors: Vec<_> = WorldAncestorsIter::<ChildOf>::new(&w, c).collect();
+        assert_eq!(ancestors, [b, a]);
+        assert_eq!(WorldAncestorsIter::<ChildOf>::new(&w, a).count(), 0);
+
+        w.entity_mut(a).insert(ChildOf(c));
+
+        let r = w
+            .run_system_once(move |q: HierarchyQuery| {
+                (
+                    q.ancestors(c).cycle_safe().collect::<Result<Vec<_>, _>>(),
+                    q.descendants_wide(a).cycle_safe().collect::<Vec<_>>(),
+                    q.descendants_deep(a).cycle_safe().collect::<Vec<_>>(),
+                )
+            })
             .unwrap();
 
-        assert_eq!(r, vec![6, 1, 0]);
+        assert_eq!(r.0, Err(HierarchyCycleError { entity: b }));
+        assert_eq!(
+            r.1,
+            [Ok(b), Ok(c), Ok(a), Err(HierarchyCycleError { entity: b })]
+        );
+        assert_eq!(
+            r.2,
+            [Ok(b), Ok(c), Ok(a), Err(HierarchyCycleError { entity: b })]
+        );
+
+        let wide: Vec<_> = WorldDescendantsWideIter::<ChildOf>::new(&w, b)
+            .cycle_safe()
+            .collect();
+        assert_eq!(
+            wide,
+            [Ok(c), Ok(a), Ok(b), Err(HierarchyCycleError { entity: c })]
+        );
     }
 
     #[test]
-    fn descendants_wide() {
+    fn cycle_safe_duplicate() {
+        use bevy_ecs::relationship::RelationshipTarget;
+
+        let mut w = World::new();
+        let a = w.spawn_empty().id();
+        let b = w.spawn(ChildOf(a)).id();
+        w.get_mut::<Children>(a)
+            .unwrap()
+            .collection_mut_risky()
+            .push(b);
+
+        // Not a cycle, but `b` is still visited twice:
+        let wide: Vec<_> = WorldDescendantsWideIter::<ChildOf>::new(&w, a)
+            .cycle_safe()
+            .collect();
+        assert_eq!(wide, [Ok(b), Err(HierarchyCycleError { entity: b })]);
+        assert_eq!(
+            HierarchyCycleError { entity: b }.to_string(),
+            format!("entity {b} was visited more than once")
+        );
+    }
+
+    #[test]
+    fn data() {
         #[derive(Component)]
-        struct A(usize);
+        struct Value(u32);
 
         let mut w = World::new();
-        let entity = w
-            .spawn(A(0))
-            .with_children(|a| {
-                a.spawn(A(1)).with_children(|b| {
-                    b.spawn(A(2)).with_children(|c| {
-                        c.spawn(A(3));
-                        c.spawn(A(4)).with_children(|d| {
-                            d.spawn(A(5));
-                        });
-                    });
-                    b.spawn(A(6)).with_children(|c| {
-                        c.spawn(A(7));
-                    });
-                });
-            })
-            .id();
+        let a = w.spawn(Value(1)).id();
+        let b = w.spawn((Value(2), ChildOf(a))).id();
+        let c = w.spawn(ChildOf(b)).id();
+        let d = w.spawn((Value(3), ChildOf(c))).id();
+        let e = w.spawn((Value(4), ChildOf(a))).id();
 
         let r = w
-            .run_system_once(move |q: HierarchyQuery, qa: Query<&A>| {
-                let mut r = Vec::new();
-                for e in q.descendants_wide(entity) {
-                    r.push(qa.get(e).unwrap().0);
-                }
-                r
+            .run_system_once(move |q: HierarchyQuery<ChildOf, &Value>| {
+                (
+                    q.children_with_data(a)
+                        .map(|(e, v)| (e, v.0))
+                        .collect::<Vec<_>>(),
+                    q.ancestors_with_data(d)
+                        .map(|(e, v)| (e, v.0))
+                        .collect::<Vec<_>>(),
+                    q.descendants_deep_with_data(a)
+                        .map(|(e, _)| e)
+                        .collect::<Vec<_>>(),
+                    q.parent_with_data(c).map(|(e, v)| (e, v.0)),
+                )
             })
             .unwrap();
 
-        assert_eq!(r, vec![1, 2, 6, 3, 4, 7, 5]);
+        assert_eq!(r.0, [(b, 2), (e, 4)]);
+        assert_eq!(r.1, [(b, 2), (a, 1)]);
+        assert_eq!(r.2, [b, d, e]);
+        assert_eq!(r.3, Some((b, 2)));
+
+        w.run_system_once(move |mut q: HierarchyQuery<ChildOf, &mut Value>| {
+            q.propagate_mut(a, |parent, mut child| child.0 += parent.0);
+            q.for_each_descendant_mut(b, |_, mut value| value.0 *= 10);
+        })
+        .unwrap();
+
+        assert_eq!(w.get::<Value>(a).unwrap().0, 1);
+        assert_eq!(w.get::<Value>(b).unwrap().0, 3);
+        assert_eq!(w.get::<Value>(d).unwrap().0, 30);
+        assert_eq!(w.get::<Value>(e).unwrap().0, 5);
     }
 
     #[test]
-    fn world_descendants_wide() {
+    fn inherit() {
+        #[derive(Component, Clone, PartialEq, Debug)]
+        struct Visible(bool);
+
+        impl Inherit for Visible {
+            fn inherit(&self, parent: &Self) -> Self {
+                Visible(self.0 && parent.0)
+            }
+        }
+
+        let mut app = App::new();
+        app.add_plugins(InheritPlugin::<Visible>::default());
+
+        let w = app.world_mut();
+        let a = w.spawn(Visible(true)).id();
+        let b = w.spawn((Visible(false), ChildOf(a))).id();
+        let c = w.spawn(ChildOf(b)).id();
+        let d = w.spawn((Visible(true), ChildOf(c))).id();
+        let e = w.spawn(Visible(true)).id();
+
+        let inherited = |app: &App, entity: Entity| {
+            app.world()
+                .get::<Inherited<Visible>>(entity)
+                .map(|inherited| inherited.0 .0)
+        };
+
+        app.update();
+        assert_eq!(inherited(&app, a), Some(true));
+        assert_eq!(inherited(&app, b), Some(false));
+        assert_eq!(inherited(&app, c), None);
+        assert_eq!(inherited(&app, d), Some(false));
+
+        app.world_mut().get_mut::<Visible>(b).unwrap().0 = true;
+        app.update();
+        assert_eq!(inherited(&app, d), Some(true));
+
+        app.world_mut().entity_mut(e).insert(Visible(false));
+        app.world_mut().entity_mut(c).insert(ChildOf(e));
+        app.update();
+        assert_eq!(inherited(&app, b), Some(true));
+        assert_eq!(inherited(&app, d), Some(false));
+
+        app.world_mut().entity_mut(c).remove::<ChildOf>();
+        app.update();
+        assert_eq!(inherited(&app, d), Some(true));
+
+        app.world_mut()
+            .entity_mut(c)
+            .insert((ChildOf(a), Visible(false)));
+        app.update();
+        assert_eq!(inherited(&app, c), Some(false));
+        assert_eq!(inherited(&app, d), Some(false));
+
+        app.world_mut().entity_mut(c).remove::<Visible>();
+        app.update();
+        assert_eq!(inherited(&app, c), None);
+        assert_eq!(inherited(&app, d), Some(true));
+    }
+
+    #[test]
+    fn ancestry_changed() {
+        let mut app = App::new();
+        app.add_plugins(AncestryChangedPlugin::<ChildOf>::default());
+
+        let changed = |app: &mut App| {
+            let mut messages = app.world_mut().resource_mut::<Messages<AncestryChanged>>();
+            messages.drain().map(|m| m.entity).collect::<HashSet<_>>()
+        };
+
+        let w = app.world_mut();
+        let a = w.spawn_empty().id();
+        let b = w.spawn(ChildOf(a)).id();
+        let c = w.spawn(ChildOf(b)).id();
+        let d = w.spawn(ChildOf(c)).id();
+        let e = w.spawn_empty().id();
+
+        app.update();
+        assert_eq!(changed(&mut app), HashSet::from_iter([b, c, d]));
+
+        app.update();
+        assert!(changed(&mut app).is_empty());
+
+        app.world_mut().entity_mut(b).insert(ChildOf(e));
+        app.world_mut().entity_mut(c).insert(ChildOf(a));
+        app.update();
+        assert_eq!(changed(&mut app), HashSet::from_iter([b, c, d]));
+
+        app.world_mut().entity_mut(c).remove::<ChildOf>();
+        app.update();
+        assert_eq!(changed(&mut app), HashSet::from_iter([c, d]));
+
+        app.world_mut().entity_mut(e).despawn();
+        app.update();
+        assert!(changed(&mut app).is_empty());
+
+        app.world_mut().entity_mut(d).insert(ChildOf(c));
+        app.update();
+        assert!(changed(&mut app).is_empty());
+    }
+
+    #[test]
+    fn ancestry_changed_payload() {
+        use crate::relationship;
+
+        relationship! {
+            #[derive(Component)]
+            struct Slots {
+                #[relationship]
+                children: Vec<Entity>,
+            } -> {
+                #[derive(Component)]
+                struct ChildOfSlot {
+                    #[relationship]
+                    parent: Entity,
+                    slot: u8,
+                }
+            }
+        }
+
+        let mut app = App::new();
+        app.add_plugins(AncestryChangedPlugin::<ChildOfSlot>::default());
+
+        let changed = |app: &mut App| {
+            let mut messages = app
+                .world_mut()
+                .resource_mut::<Messages<AncestryChanged<ChildOfSlot>>>();
+            messages.drain().map(|m| m.entity).collect::<HashSet<_>>()
+        };
+
+        let w = app.world_mut();
+        let a = w.spawn_empty().id();
+        let b = w.spawn(ChildOfSlot { parent: a, slot: 0 }).id();
+        let c = w.spawn(ChildOfSlot { parent: b, slot: 0 }).id();
+
+        app.update();
+        assert_eq!(changed(&mut app), HashSet::from_iter([b, c]));
+
+        app.world_mut()
+            .entity_mut(b)
+            .insert(ChildOfSlot { parent: a, slot: 1 });
+        app.update();
+        assert_eq!(app.world().get::<ChildOfSlot>(b).unwrap().slot, 1);
+        assert!(changed(&mut app).is_empty());
+
+        app.world_mut().entity_mut(b).remove::<ChildOfSlot>();
+        app.world_mut()
+            .entity_mut(b)
+            .insert(ChildOfSlot { parent: a, slot: 2 });
+        app.update();
+        assert!(changed(&mut app).is_empty());
+
+        app.world_mut().entity_mut(b).remove::<ChildOfSlot>();
+        app.update();
+        assert_eq!(changed(&mut app), HashSet::from_iter([b, c]));
+    }
+
+    #[test]
+    fn dump() {
         #[derive(Component)]
-        struct A(usize);
+        struct Foo;
+
+        #[derive(Component)]
+        struct Bar;
 
         let mut w = World::new();
-        let entity = w
-            .spawn(A(0))
-            .with_children(|a| {
-                a.spawn(A(1)).with_children(|b| {
-                    b.spawn(A(2)).with_children(|c| {
-                        c.spawn(A(3));
-                        c.spawn(A(4)).with_children(|d| {
-                            d.spawn(A(5));
-                        });
-                    });
-                    b.spawn(A(6)).with_children(|c| {
-                        c.spawn(A(7));
-                    });
-                });
-            })
-            .id();
+        let a = w.spawn((Name::new("A"), Foo, Bar)).id();
+        let b = w.spawn((Name::new("B"), Bar, ChildOf(a))).id();
+        let c = w.spawn(ChildOf(b)).id();
+        let d = w.spawn((Name::new("D"), ChildOf(b))).id();
+        let e = w.spawn((Name::new("E"), Foo, ChildOf(a))).id();
 
-        let r: Vec<_> = WorldDescendantsWideIter::<ChildOf>::new(&w, entity)
-            .filter_map(|entity| w.get::<A>(entity))
-            .map(|&A(v)| v)
-            .collect();
+        assert_eq!(
+            dump_hierarchy::<ChildOf>(&w, a),
+            format!(
+                "{a} \"A\"\n\
+                |-- {b} \"B\"\n\
+                |   |-- {c}\n\
+                |   `-- {d} \"D\"\n\
+                `-- {e} \"E\"\n"
+            )
+        );
 
-        assert_eq!(r, vec![1, 2, 6, 3, 4, 7, 5]);
+        assert_eq!(
+            HierarchyDump::<ChildOf>::new(&w, a)
+                .style(HierarchyDumpStyle::Markdown)
+                .component::<Foo>()
+                .component::<Bar>()
+                .to_string(),
+            format!(
+                "- {a} \"A\" [Bar, Foo]\n\
+                \x20 - {b} \"B\" [Bar]\n\
+                \x20   - {c}\n\
+                \x20   - {d} \"D\"\n\
+                \x20 - {e} \"E\" [Foo]\n"
+            )
+        );
+
+        w.entity_mut(a).insert(ChildOf(e));
+        w.get_mut::<Children>(b)
+            .unwrap()
+            .collection_mut_risky()
+            .push(d);
+
+        assert_eq!(
+            dump_hierarchy::<ChildOf>(&w, a),
+            format!(
+                "{a} \"A\"\n\
+                |-- {b} \"B\"\n\
+                |   |-- {c}\n\
+                |   |-- {d} \"D\"\n\
+                |   `-- (repeated: {d})\n\
+                `-- {e} \"E\"\n\
+                \x20   `-- (cycle: {a})\n"
+            )
+        );
+
+        let dump = HierarchyDump::<ChildOf>::new(&w, c)
+            .all_components()
+            .to_string();
+        assert!(dump.starts_with(&format!("{c} [")));
+        assert_eq!(dump.lines().count(), 1);
     }
 
     #[test]
-    fn descendants_deep() {
+    fn clone() {
+        #[derive(Component, Clone)]
+        struct Target(#[entities] Entity);
+
         #[derive(Component)]
-        struct A(usize);
+        #[relationship(relationship_target = Owned)]
+        struct OwnedBy(Entity);
+
+        #[derive(Component)]
+        #[relationship_target(relationship = OwnedBy)]
+        struct Owned(Vec<Entity>);
 
         let mut w = World::new();
-        let entity = w
-            .spawn(A(0))
-            .with_children(|a| {
-                a.spawn(A(1)).with_children(|b| {
-                    b.spawn(A(2)).with_children(|c| {
-                        c.spawn(A(3));
-                        c.spawn(A(4)).with_children(|d| {
-                            d.spawn(A(5));
-                        });
-                    });
-                    b.spawn(A(6)).with_children(|c| {
-                        c.spawn(A(7));
-                    });
-                });
-            })
-            .id();
+        let x = w.spawn_empty().id();
+        let p = w.spawn_empty().id();
+        let a = w.spawn(ChildOf(p)).id();
+        let b = w.spawn((ChildOf(a), Target(x))).id();
+        let c = w.spawn((ChildOf(a), Target(b))).id();
 
-        let r = w
-            .run_system_once(move |q: HierarchyQuery, qa: Query<&A>| {
-                let mut r = Vec::new();
-                for e in q.descendants_deep(entity) {
-                    r.push(qa.get(e).unwrap().0);
-                }
-                r
+        let map = clone_subtree::<ChildOf>(w.entity_mut(a)).unwrap();
+        assert_eq!(map.len(), 3);
+
+        let (a2, b2, c2) = (map[&a], map[&b], map[&c]);
+        assert_eq!(w.get::<ChildOf>(a2).unwrap().parent(), p);
+        assert_eq!(world_children::<ChildOf>(&w, a2), [b2, c2]);
+        assert_eq!(world_children::<ChildOf>(&w, p), [a, a2]);
+        assert_eq!(w.get::<Target>(b2).unwrap().0, x);
+        assert_eq!(w.get::<Target>(c2).unwrap().0, b2);
+        assert_eq!(world_children::<ChildOf>(&w, a), [b, c]);
+
+        let d = w.spawn_empty().id();
+        let e = w.spawn(OwnedBy(d)).id();
+        let map = clone_subtree::<OwnedBy>(w.entity_mut(d)).unwrap();
+        let (d2, e2) = (map[&d], map[&e]);
+        assert_eq!(w.get::<OwnedBy>(e2).unwrap().0, d2);
+        assert_eq!(w.get::<Owned>(d2).unwrap().0, [e2]);
+
+        w.entity_mut(d).insert(OwnedBy(e));
+        let count = w.entities().count_spawned();
+        assert_eq!(
+            clone_subtree::<OwnedBy>(w.entity_mut(d)),
+            Err(HierarchyCycleError { entity: d })
+        );
+        assert_eq!(w.entities().count_spawned(), count);
+    }
+
+    #[test]
+    fn order_children() {
+        #[derive(Component, PartialEq, Eq, PartialOrd, Ord)]
+        struct Order(i32);
+
+        let mut app = App::new();
+        app.add_plugins(SortChildrenPlugin::<Order>::default());
+
+        let w = app.world_mut();
+        let p = w.spawn_empty().id();
+        let a = w.spawn((Order(3), ChildOf(p))).id();
+        let b = w.spawn(ChildOf(p)).id();
+        let c = w.spawn((Order(1), ChildOf(p))).id();
+        let d = w.spawn((Order(1), ChildOf(p))).id();
+
+        w.entity_mut(p)
+            .sort_children_by_key::<ChildOf, &Order, _>(|order| -order.0);
+        assert_eq!(world_children::<ChildOf>(w, p), [a, c, d, b]);
+
+        let e = w.spawn_empty().id();
+        w.commands().entity(p).insert_child_at::<ChildOf>(1, e);
+        w.commands().entity(p).move_child_to::<ChildOf>(a, 10);
+        w.commands().entity(p).move_child_to::<ChildOf>(e, 0);
+        w.flush();
+        assert_eq!(world_children::<ChildOf>(w, p), [e, c, d, b, a]);
+
+        app.update();
+        let w = app.world_mut();
+        assert_eq!(world_children::<ChildOf>(w, p), [c, d, a, e, b]);
+
+        w.get_mut::<Order>(c).unwrap().0 = 5;
+        app.update();
+        let w = app.world_mut();
+        assert_eq!(world_children::<ChildOf>(w, p), [d, a, c, e, b]);
+
+        w.entity_mut(a).remove::<Order>();
+        app.update();
+        let w = app.world_mut();
+        assert_eq!(world_children::<ChildOf>(w, p), [d, c, a, e, b]);
+    }
+
+    #[test]
+    fn graph() {
+        crate::relationship! {
+            #[derive(Component)]
+            pub struct Owned(Vec<Entity>) -> {
+                #[derive(Component)]
+                pub struct OwnedBy(pub Entity)
+            }
+        }
+
+        #[derive(Component)]
+        #[relationship(relationship_target = Attachments)]
+        struct AttachedTo(Entity);
+
+        #[derive(Component)]
+        #[relationship_target(relationship = AttachedTo)]
+        struct Attachments(Vec<Entity>);
+
+        type Graph = (ChildOf, OwnedBy, AttachedTo);
+
+        let mut w = World::new();
+        let player = w.spawn_empty().id();
+        let sword = w.spawn(OwnedBy(player)).id();
+        let gem = w.spawn(AttachedTo(sword)).id();
+        let bag = w.spawn((ChildOf(player), OwnedBy(player))).id();
+        let coin = w.spawn((ChildOf(bag), OwnedBy(player))).id();
+        let other = w.spawn(OwnedBy(gem)).id();
+
+        let steps: Vec<_> = traverse_graph_wide::<Graph>(&w, player)
+            .map(|step| (step.entity, step.source, step.edge.index(), step.depth))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                (bag, player, 0, 1),
+                (sword, player, 1, 1),
+                (coin, player, 1, 1),
+                (gem, sword, 2, 2),
+                (other, gem, 1, 3),
+            ]
+        );
+
+        let steps: Vec<_> = traverse_graph_deep::<Graph>(&w, player)
+            .map(|step| (step.entity, step.source, step.edge.index()))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                (bag, player, 0),
+                (coin, bag, 0),
+                (sword, player, 1),
+                (gem, sword, 2),
+                (other, gem, 1),
+            ]
+        );
+
+        let steps: Vec<_> = w
+            .run_system_once(move |q: GraphQuery<Graph>| {
+                q.traverse_wide(player)
+                    .filter(|step| step.edge.is::<AttachedTo>())
+                    .map(|step| step.entity)
+                    .collect::<Vec<_>>()
             })
             .unwrap();
+        assert_eq!(steps, [gem]);
 
-        assert_eq!(r, vec![1, 2, 3, 4, 5, 6, 7]);
+        w.entity_mut(player).insert(OwnedBy(other));
+        assert_eq!(traverse_graph_deep::<Graph>(&w, player).count(), 5);
     }
 
     #[test]
-    fn world_descendants_deep() {
+    fn par_for_each() {
+        use bevy_ecs::relationship::RelationshipHookMode;
+
         #[derive(Component)]
-        struct A(usize);
+        struct Value(usize);
 
         let mut w = World::new();
-        let entity = w
-            .spawn(A(0))
-            .with_children(|a| {
-                a.spawn(A(1)).with_children(|b| {
-                    b.spawn(A(2)).with_children(|c| {
-                        c.spawn(A(3));
-                        c.spawn(A(4)).with_children(|d| {
-                            d.spawn(A(5));
-                        });
-                    });
-                    b.spawn(A(6)).with_children(|c| {
-                        c.spawn(A(7));
-                    });
-                });
-            })
-            .id();
+        let root = w.spawn(Value(0)).id();
+        let mut entities = vec![root];
+        for i in 1..1000 {
+            let parent = entities[(i - 1) / 3];
+            entities.push(w.spawn((Value(0), ChildOf(parent))).id());
+        }
 
-        let r: Vec<_> = WorldDescendantsDeepIter::<ChildOf>::new(&w, entity)
-            .filter_map(|entity| w.get::<A>(entity))
-            .map(|&A(v)| v)
-            .collect();
+        // Make the hierarchy cyclic; the root must still only be visited once.
+        w.entity_mut(root)
+            .insert_with_relationship_hook_mode(ChildOf(entities[10]), RelationshipHookMode::Skip);
+        w.entity_mut(entities[10])
+            .get_mut::<Children>()
+            .unwrap()
+            .collection_mut_risky()
+            .push(root);
 
-        assert_eq!(r, vec![1, 2, 3, 4, 5, 6, 7]);
+        w.run_system_once(move |mut q: HierarchyQuery<ChildOf, &mut Value>| {
+            q.par_for_each_descendant_mut(root, |_, mut value| value.0 += 1);
+        })
+        .unwrap();
+
+        assert_eq!(w.get::<Value>(root).unwrap().0, 0);
+        assert!(entities[1..]
+            .iter()
+            .all(|&entity| w.get::<Value>(entity).unwrap().0 == 1));
+    }
+
+    #[test]
+    fn subtree_commands() {
+        crate::relationship! {
+            #[derive(Component)]
+            pub struct Owned(Vec<Entity>) -> [linked_spawn] {
+                #[derive(Component)]
+                pub struct OwnedBy(pub Entity)
+            }
+        }
+
+        #[derive(Component)]
+        struct Marked;
+
+        let mut w = World::new();
+        let a = w.spawn_empty().id();
+        let b = w.spawn((Marked, OwnedBy(a))).id();
+        let c = w.spawn(OwnedBy(b)).id();
+        let d = w.spawn((Marked, OwnedBy(c))).id();
+        let e = w.spawn(OwnedBy(a)).id();
+        let f = w.spawn((Marked, OwnedBy(e))).id();
+        let g = w.spawn(OwnedBy(f)).id();
+        let h = w.spawn(OwnedBy(e)).id();
+
+        despawn_descendants_where::<OwnedBy, With<Marked>>(w.entity_mut(a));
+        for entity in [b, c, d, f, g] {
+            assert!(w.get_entity(entity).is_err());
+        }
+        assert_eq!(world_children::<OwnedBy>(&w, a), [e]);
+        assert_eq!(world_children::<OwnedBy>(&w, e), [h]);
+
+        w.commands()
+            .entity(a)
+            .queue(reparent_subtree::<OwnedBy>(h, 0));
+        w.flush();
+        assert_eq!(world_parent::<OwnedBy>(&w, a), None);
+
+        w.commands()
+            .entity(h)
+            .queue(reparent_subtree::<OwnedBy>(a, 0));
+        w.flush();
+        assert_eq!(world_children::<OwnedBy>(&w, a), [h, e]);
+        assert!(world_children::<OwnedBy>(&w, e).is_empty());
+
+        w.commands().entity(e).queue(detach_subtree::<OwnedBy>);
+        w.flush();
+        assert_eq!(world_parent::<OwnedBy>(&w, e), None);
+        assert_eq!(world_children::<OwnedBy>(&w, a), [h]);
+    }
+
+    #[test]
+    fn fold() {
+        #[derive(Component)]
+        struct Weight(u32);
+
+        let mut w = World::new();
+        let a = w.spawn(Weight(1)).id();
+        let b = w.spawn((Weight(2), ChildOf(a))).id();
+        let c = w.spawn(ChildOf(b)).id();
+        let d = w.spawn((Weight(4), ChildOf(c))).id();
+        let e = w.spawn((Weight(8), ChildOf(b))).id();
+        let f = w.spawn((Weight(16), ChildOf(a))).id();
+
+        let total = |_: Entity, weight: Option<&Weight>, children: Vec<u32>| {
+            weight.map_or(0, |weight| weight.0) + children.iter().sum::<u32>()
+        };
+        assert_eq!(fold_subtree::<ChildOf, &Weight, _>(&w, a, total), 31);
+        assert_eq!(fold_subtree::<ChildOf, &Weight, _>(&w, c, total), 4);
+
+        let r = w
+            .run_system_once(move |q: HierarchyQuery<ChildOf, &Weight>| {
+                let total = q.fold_subtree(a, total);
+                let order = q.fold_subtree(a, |entity, _, children: Vec<Vec<Entity>>| {
+                    let mut order: Vec<Entity> = children.into_iter().flatten().collect();
+                    order.push(entity);
+                    order
+                });
+                (total, order)
+            })
+            .unwrap();
+        assert_eq!(r.0, 31);
+        assert_eq!(r.1, [d, c, e, b, f, a]);
     }
 }
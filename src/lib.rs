@@ -15,11 +15,13 @@ pub mod system;
 pub mod prelude {
     //! Prelude module to import the most essential utilities.
 
-    pub use crate::component::{Merge, MergeComponent, MergeFrom, MergeWith};
+    pub use crate::component::{
+        LinkEntities, LinkedBy, Links, Merge, MergeBundle, MergeComponent, MergeFrom, MergeWith,
+    };
     pub use crate::defer::{run_deferred_systems, RunDeferredSystem};
     pub use crate::event::{AddSingleObserver, OnSingle, SingleEvent, TriggerSingle};
     pub use crate::expect::Expect;
-    pub use crate::query::{Get, MapQuery};
+    pub use crate::query::{Get, GetMut, MapQuery, MapQueryMut};
     pub use crate::reflect::Registerable;
     pub use crate::spawn::{SpawnUnrelated, WithChild};
     pub use crate::Static;
@@ -27,6 +29,13 @@ pub mod prelude {
     pub use crate::relationship;
 }
 
+#[doc(hidden)]
+pub mod __private {
+    //! Re-exports used by macros in this crate.
+
+    pub use bevy_reflect::Reflect;
+}
+
 /// Wrapper for [`disqualified::ShortName`] since it was removed from Bevy standard.
 ///
 /// This avoids the need to add a dependency on [`disqualified`] if you're already using `moonshine` crates.
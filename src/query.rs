@@ -23,6 +23,94 @@ pub trait MapQuery {
     fn map(data: <Self::Query as QueryData>::Item<'_, '_>) -> Self::Output;
 }
 
+/// Derives [`MapQuery`](trait@MapQuery) for a struct whose fields are each mapped from a component.
+///
+/// # Usage
+///
+/// Each field must be annotated with `#[map_query(Component)]`, which clones the field from the given component,
+/// or `#[map_query(Component, map = path)]`, which maps the field from a reference to the given component using
+/// the given function.
+///
+/// The output of the query is the struct itself. This requires the `derive` feature.
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::prelude::*;
+///
+/// #[derive(MapQuery)]
+/// struct PlayerView {
+///     #[map_query(Name)]
+///     name: Name,
+///     #[map_query(Transform, map = height)]
+///     height: f32,
+/// }
+///
+/// fn height(transform: &Transform) -> f32 {
+///     transform.translation.y
+/// }
+///
+/// fn players(query: Query<Get<PlayerView>>) -> Vec<(String, f32)> {
+///     query
+///         .iter()
+///         .map(|PlayerView { name, height }| (name.to_string(), height))
+///         .collect()
+/// }
+///
+/// # use bevy::ecs::system::RunSystemOnce;
+/// let mut world = World::new();
+/// world.spawn((Name::new("Alice"), Transform::from_xyz(0.0, 2.0, 0.0)));
+/// world.spawn(Name::new("Bob"));
+///
+/// assert_eq!(world.run_system_once(players).unwrap(), [("Alice".to_string(), 2.0)]);
+/// ```
+///
+/// Tuple structs and generic structs are also supported:
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::prelude::*;
+///
+/// #[derive(Component, Clone, PartialEq, Debug)]
+/// struct Health(u32);
+///
+/// #[derive(MapQuery)]
+/// struct Labeled<T: Component + Clone>(
+///     #[map_query(Name, map = Name::to_string)] String,
+///     #[map_query(T)] T,
+/// );
+///
+/// fn health(query: Query<Get<Labeled<Health>>>) -> Vec<(String, Health)> {
+///     query.iter().map(|Labeled(name, health)| (name, health)).collect()
+/// }
+///
+/// # use bevy::ecs::system::RunSystemOnce;
+/// let mut world = World::new();
+/// world.spawn((Name::new("Alice"), Health(10)));
+///
+/// assert_eq!(world.run_system_once(health).unwrap(), [("Alice".to_string(), Health(10))]);
+/// ```
+///
+/// If this crate is renamed in `Cargo.toml`, its new path must be given using `#[map_query(crate = path)]`:
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util as util;
+///
+/// #[derive(util::query::MapQuery)]
+/// #[map_query(crate = util)]
+/// struct Position(#[map_query(Transform, map = translation)] Vec3);
+///
+/// fn translation(transform: &Transform) -> Vec3 {
+///     transform.translation
+/// }
+///
+/// # bevy_ecs::system::assert_is_system(|_: Query<util::query::Get<Position>>| {});
+/// ```
+#[cfg(feature = "derive")]
+pub use moonshine_util_derive::MapQuery;
+
 /// A query decorator which maps some query data into `T` using [`MapQuery`].
 ///
 /// This is useful for when you want to compute a processed value from some query data.
@@ -147,3 +235,162 @@ unsafe impl<T: MapQuery> QueryData for Get<T> {
 unsafe impl<T: MapQuery> IterQueryData for Get<T> {}
 
 unsafe impl<T: MapQuery> ReadOnlyQueryData for Get<T> {}
+
+/// A trait for types that can be constructed from mutable query data.
+///
+/// This is similar to [`MapQuery`], except that the query data may be mutable, and the output may borrow from it.
+///
+/// Implementations must use [`QueryItem`](bevy_ecs::query::QueryItem) and `Self::Output` in the signatures
+/// of [`map`](MapQueryMut::map) and [`shrink`](MapQueryMut::shrink), as shown in the [`GetMut`] example.
+///
+/// See [`GetMut`] for more information on usage.
+pub trait MapQueryMut {
+    /// The query type which this type can be constructed from.
+    type Query: QueryData;
+
+    /// The output type that this query will map to.
+    type Output<'w, 's>;
+
+    /// Called at the time of query execution to map the query data into `Self`.
+    fn map<'w, 's>(data: <Self::Query as QueryData>::Item<'w, 's>) -> Self::Output<'w, 's>;
+
+    /// Shortens the lifetime of the output.
+    ///
+    /// This is usually implemented as an identity function, i.e. `output`.
+    fn shrink<'wlong: 'wshort, 'wshort, 's>(
+        output: Self::Output<'wlong, 's>,
+    ) -> Self::Output<'wshort, 's>;
+}
+
+/// A query decorator which maps some mutable query data into `T` using [`MapQueryMut`].
+///
+/// This is useful for when you want to expose a mutable view or proxy of some query data.
+///
+/// Note that read-only access to this query (i.e. [`Query::iter`] or [`Query::get`]) returns the read-only
+/// query data of [`MapQueryMut::Query`] without any mapping.
+///
+/// # Example
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use bevy::ecs::query::QueryItem;
+/// use bevy::ecs::system::RunSystemOnce;
+/// use moonshine_util::prelude::*;
+///
+/// struct Height;
+///
+/// impl MapQueryMut for Height {
+///     type Query = &'static mut Transform;
+///
+///     type Output<'w, 's> = &'w mut f32;
+///
+///     fn map<'w, 's>(data: QueryItem<'w, 's, Self::Query>) -> Self::Output<'w, 's> {
+///         &mut data.into_inner().translation.y
+///     }
+///
+///     fn shrink<'wlong: 'wshort, 'wshort, 's>(
+///         output: Self::Output<'wlong, 's>,
+///     ) -> Self::Output<'wshort, 's> {
+///         output
+///     }
+/// }
+///
+/// fn jump(mut query: Query<GetMut<Height>>) {
+///     for height in query.iter_mut() {
+///         *height += 1.0;
+///     }
+/// }
+///
+/// let mut world = World::new();
+/// let entity = world.spawn(Transform::default()).id();
+///
+/// world.run_system_once(jump).unwrap();
+/// assert_eq!(world.get::<Transform>(entity).unwrap().translation.y, 1.0);
+/// ```
+pub struct GetMut<T>(PhantomData<T>);
+
+unsafe impl<T: MapQueryMut> WorldQuery for GetMut<T> {
+    type Fetch<'a> = <T::Query as WorldQuery>::Fetch<'a>;
+
+    type State = <T::Query as WorldQuery>::State;
+
+    fn shrink_fetch<'wlong: 'wshort, 'wshort>(fetch: Self::Fetch<'wlong>) -> Self::Fetch<'wshort> {
+        T::Query::shrink_fetch(fetch)
+    }
+
+    unsafe fn init_fetch<'w>(
+        world: bevy_ecs::world::unsafe_world_cell::UnsafeWorldCell<'w>,
+        state: &Self::State,
+        last_run: Tick,
+        this_run: Tick,
+    ) -> Self::Fetch<'w> {
+        unsafe { T::Query::init_fetch(world, state, last_run, this_run) }
+    }
+
+    const IS_DENSE: bool = T::Query::IS_DENSE;
+
+    unsafe fn set_archetype<'w>(
+        fetch: &mut Self::Fetch<'w>,
+        state: &Self::State,
+        archetype: &'w Archetype,
+        table: &'w Table,
+    ) {
+        unsafe { T::Query::set_archetype(fetch, state, archetype, table) }
+    }
+
+    unsafe fn set_table<'w>(fetch: &mut Self::Fetch<'w>, state: &Self::State, table: &'w Table) {
+        unsafe { T::Query::set_table(fetch, state, table) }
+    }
+
+    fn update_component_access(state: &Self::State, access: &mut FilteredAccess) {
+        T::Query::update_component_access(state, access)
+    }
+
+    fn init_state(world: &mut World) -> Self::State {
+        T::Query::init_state(world)
+    }
+
+    fn get_state(components: &Components) -> Option<Self::State> {
+        T::Query::get_state(components)
+    }
+
+    fn matches_component_set(
+        state: &Self::State,
+        set_contains_id: &impl Fn(ComponentId) -> bool,
+    ) -> bool {
+        T::Query::matches_component_set(state, set_contains_id)
+    }
+}
+
+unsafe impl<T: MapQueryMut> QueryData for GetMut<T> {
+    type ReadOnly = <T::Query as QueryData>::ReadOnly;
+
+    const IS_READ_ONLY: bool = <T::Query as QueryData>::IS_READ_ONLY;
+
+    const IS_ARCHETYPAL: bool = <T::Query as QueryData>::IS_ARCHETYPAL;
+
+    type Item<'w, 's> = T::Output<'w, 's>;
+
+    fn shrink<'wlong: 'wshort, 'wshort, 's>(
+        item: Self::Item<'wlong, 's>,
+    ) -> Self::Item<'wshort, 's> {
+        T::shrink(item)
+    }
+
+    unsafe fn fetch<'w, 's>(
+        state: &'s Self::State,
+        fetch: &mut Self::Fetch<'w>,
+        entity: Entity,
+        table_row: TableRow,
+    ) -> Option<Self::Item<'w, 's>> {
+        T::Query::fetch(state, fetch, entity, table_row).map(T::map)
+    }
+
+    fn iter_access(
+        state: &Self::State,
+    ) -> impl Iterator<Item = bevy_ecs::query::EcsAccessType<'_>> {
+        T::Query::iter_access(state)
+    }
+}
+
+unsafe impl<T: MapQueryMut> IterQueryData for GetMut<T> where T::Query: IterQueryData {}
@@ -1,5 +1,8 @@
 //! Utilities related to reflection.
 
+use bevy_app::App;
+use bevy_ecs::reflect::ReflectComponent;
+use bevy_ecs::relationship::Relationship;
 use bevy_reflect::{FromReflect, GetTypeRegistration, Typed};
 
 /// Convenient alias for [`GetTypeRegistration`] + [`Typed`].
@@ -24,3 +27,35 @@ use bevy_reflect::{FromReflect, GetTypeRegistration, Typed};
 pub trait Registerable: GetTypeRegistration + Typed + FromReflect {}
 
 impl<T: GetTypeRegistration + Typed + FromReflect> Registerable for T {}
+
+/// Registers both sides of a [`Relationship`] for reflection, including their [`ReflectComponent`] type data.
+///
+/// # Usage
+///
+/// This function may be used as a [`Plugin`](bevy_app::Plugin) directly. It is especially useful with
+/// the `reflect` option of the [`relationship!`](crate::relationship) macro:
+///
+/// ```rust
+/// use bevy::prelude::*;
+/// use moonshine_util::prelude::*;
+/// use moonshine_util::reflect::register_relationship;
+///
+/// relationship! {
+///     #[derive(Component)]
+///     pub struct Friends(Vec<Entity>) -> [reflect] {
+///         #[derive(Component)]
+///         pub struct FriendOf(pub Entity)
+///     }
+/// }
+///
+/// App::new().add_plugins(register_relationship::<FriendOf>);
+/// ```
+pub fn register_relationship<R: Relationship + Registerable>(app: &mut App)
+where
+    R::RelationshipTarget: Registerable,
+{
+    app.register_type::<R>()
+        .register_type_data::<R, ReflectComponent>()
+        .register_type::<R::RelationshipTarget>()
+        .register_type_data::<R::RelationshipTarget, ReflectComponent>();
+}